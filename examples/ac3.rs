@@ -1,4 +1,4 @@
-use advent_of_code::ac3::{PairwiseConstraint, AC3};
+use advent_of_code::ac3::AC3;
 
 fn main() {
     pretty_env_logger::init();
@@ -7,10 +7,8 @@ fn main() {
     ac3.add_domain("A", vec![1, 2, 3]);
     ac3.add_domain("B", vec![1, 2, 3]);
     ac3.add_domain("C", vec![1, 2, 3]);
-    ac3.add_constraint("A", "B", PairwiseConstraint::rule(|a, b| a > b));
-    ac3.add_constraint("B", "A", PairwiseConstraint::rule(|b, a| b < a));
-    ac3.add_constraint("B", "C", PairwiseConstraint::rule(|b, c| b == c));
-    ac3.add_constraint("C", "B", PairwiseConstraint::rule(|c, b| c == b));
+    ac3.add_binary_constraint("A", "B", |a, b| a > b);
+    ac3.add_binary_constraint("B", "C", |b, c| b == c);
     let data = ac3.solve().unwrap();
     println!("{:?}", data);
 }
@@ -1,127 +1,954 @@
-use anyhow::Result;
-use std::{collections::HashMap, fmt, hash::Hash, marker::PhantomData};
+use crate::grid::{fixed_grid::FixedGrid, grid_types::GridWidth, point::Point};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+    hash::Hash,
+    marker::PhantomData,
+    rc::Rc,
+};
+
+mod all_different;
+mod domain;
+mod dot;
+mod table;
+mod trace;
+pub use all_different::AllDifferent;
+pub use domain::{BitDomain, Domain};
+pub use table::TableConstraint;
+use trace::Observer;
+pub use trace::{Removal, SolveTrace};
 
 pub trait Class: Copy + Hash + Eq + fmt::Debug {}
 impl<T> Class for T where T: Copy + Hash + Eq + fmt::Debug {}
 
 pub trait Constraint {
-    type D;
-    fn apply(&self, xdomain: &mut Vec<Self::D>, ydomain: &[Self::D]) -> bool;
+    type D: Domain;
+    fn apply(&self, xdomain: &mut Self::D, ydomain: &Self::D) -> bool;
+
+    /// Revise `xdomain` the way `strategy` asks. Constraints that keep no
+    /// bookkeeping of their own can leave this as plain [`Constraint::apply`].
+    fn apply_with(&self, strategy: Strategy, xdomain: &mut Self::D, ydomain: &Self::D) -> bool {
+        let _ = strategy;
+        self.apply(xdomain, ydomain)
+    }
+}
+
+/// How arcs look for support when they are revised.
+///
+/// Only constraints that keep extra state, such as [`TableConstraint`], do
+/// anything different; the rest always behave as [`Strategy::Ac3`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Strategy {
+    /// Check every value against the other domain from scratch
+    #[default]
+    Ac3,
+    /// Count the supports of each value, and only look at what was removed
+    Ac4,
+    /// Remember the last support found for each value
+    Ac2001,
+}
+
+/// A boxed [`Constraint`], with an optional name for display.
+pub struct Rule<D>(Box<dyn Constraint<D = D>>, Option<String>);
+
+/// A constraint over any number of classes at once.
+///
+/// `domains` holds the domain of each class in the order they were given to
+/// [`AC3::add_global_constraint`]. Values may only be removed, and applying
+/// the constraint twice in a row must not remove anything the second time.
+pub trait GlobalConstraint {
+    type D: Domain;
+    fn apply(&self, domains: &mut [Self::D]) -> bool;
+}
+
+struct Global<C, D> {
+    classes: Vec<C>,
+    rule: Box<dyn GlobalConstraint<D = D>>,
 }
 
-pub struct Rule<T>(Box<dyn Constraint<D = T>>);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Work<C> {
+    Arc(C, C),
+    Global(usize),
+}
 
-struct Agenda<C> {
-    inner: HashMap<(C, C), bool>,
+/// Work waiting to be done, in FIFO order with no duplicates.
+#[derive(Clone)]
+struct Agenda<K> {
+    queue: VecDeque<K>,
+    queued: HashSet<K>,
 }
 
-impl<C> Default for Agenda<C> {
+impl<K> Default for Agenda<K> {
     fn default() -> Self {
         Agenda {
-            inner: HashMap::new(),
+            queue: VecDeque::new(),
+            queued: HashSet::new(),
         }
     }
 }
 
-impl<C: Class> Agenda<C> {
-    fn pop(&mut self) -> Option<(C, C)> {
-        self.inner
-            .iter_mut()
-            .filter(|(_, v)| **v)
-            .map(|(k, v)| {
-                *v = false;
-                *k
-            })
-            .next()
+impl<K: Copy + Hash + Eq> Agenda<K> {
+    fn pop(&mut self) -> Option<K> {
+        let arc = self.queue.pop_front()?;
+        self.queued.remove(&arc);
+        Some(arc)
     }
-    fn extend(&mut self, iter: impl Iterator<Item = (C, C)>) {
+    fn extend(&mut self, iter: impl Iterator<Item = K>) {
         for k in iter {
-            self.inner.insert(k, true);
+            if self.queued.insert(k) {
+                self.queue.push_back(k);
+            }
         }
     }
     fn len(&self) -> usize {
-        self.inner.iter().filter(|(_, v)| **v).count()
+        self.queue.len()
     }
 }
 
-#[derive(Default)]
-pub struct Arcs<C: Class, T> {
-    rules: HashMap<(C, C), MultiConstraint<T>>,
+pub struct Arcs<C: Class, D: Domain> {
+    rules: HashMap<(C, C), MultiConstraint<D>>,
+    // y -> every x with an arc (x, y)
+    by_target: HashMap<C, Vec<C>>,
+    // x -> every y with an arc (x, y)
+    by_source: HashMap<C, Vec<C>>,
+    globals: Vec<Global<C, D>>,
+    // class -> every global constraint it is part of
+    globals_for: HashMap<C, Vec<usize>>,
+    unary: HashMap<C, Vec<Box<UnaryPredicate<D::Value>>>>,
+    strategy: Strategy,
+    // the work and unary classes each new constraint needs, in the order
+    // they were added, so a restore can queue what came after a snapshot
+    added: Vec<Work<C>>,
+    added_unary: Vec<C>,
+}
+
+impl<C: Class, D: Domain> Default for Arcs<C, D> {
+    fn default() -> Self {
+        Arcs {
+            rules: HashMap::new(),
+            by_target: HashMap::new(),
+            by_source: HashMap::new(),
+            globals: Vec::new(),
+            globals_for: HashMap::new(),
+            unary: HashMap::new(),
+            strategy: Strategy::default(),
+            added: Vec::new(),
+            added_unary: Vec::new(),
+        }
+    }
 }
 
-impl<C: Class, T> Arcs<C, T> {
-    fn add_constraint(&mut self, x: C, y: C, rule: Rule<T>) {
-        let rules = self.rules.entry((x, y)).or_default();
+impl<C: Class, D: Domain> Arcs<C, D> {
+    fn add_constraint(&mut self, x: C, y: C, rule: Rule<D>) {
+        let (by_target, by_source) = (&mut self.by_target, &mut self.by_source);
+        let rules = self.rules.entry((x, y)).or_insert_with(|| {
+            by_target.entry(y).or_default().push(x);
+            by_source.entry(x).or_default().push(y);
+            MultiConstraint::default()
+        });
         rules.0.push(rule);
+        self.added.push(Work::Arc(x, y));
+    }
+
+    fn add_unary(&mut self, class: C, f: Box<UnaryPredicate<D::Value>>) {
+        self.unary.entry(class).or_default().push(f);
+        self.added_unary.push(class);
+    }
+
+    fn add_global(&mut self, classes: Vec<C>, rule: Box<dyn GlobalConstraint<D = D>>) -> usize {
+        let idx = self.globals.len();
+        for c in &classes {
+            self.globals_for.entry(*c).or_default().push(idx);
+        }
+        self.globals.push(Global { classes, rule });
+        self.added.push(Work::Global(idx));
+        idx
     }
 
-    fn get(&self, x: C, y: C) -> Option<&MultiConstraint<T>> {
+    fn get(&self, x: C, y: C) -> Option<&MultiConstraint<D>> {
         self.rules.get(&(x, y))
     }
     fn all_arcs(&self) -> impl Iterator<Item = (C, C)> + '_ {
         self.rules.keys().cloned()
     }
     fn arcs_for_y(&self, q: C) -> impl Iterator<Item = (C, C)> + '_ {
-        self.all_arcs().filter(move |(_, y)| q == *y)
+        self.by_target
+            .get(&q)
+            .into_iter()
+            .flatten()
+            .map(move |x| (*x, q))
+    }
+
+    fn all_work(&self) -> impl Iterator<Item = Work<C>> + '_ {
+        let arcs = self.all_arcs().map(|(x, y)| Work::Arc(x, y));
+        arcs.chain((0..self.globals.len()).map(Work::Global))
+    }
+    /// Everything to look at again once the domain of `q` shrinks
+    fn work_for(&self, q: C) -> impl Iterator<Item = Work<C>> + '_ {
+        let arcs = self.arcs_for_y(q).map(|(x, y)| Work::Arc(x, y));
+        let globals = self.globals_for.get(&q).into_iter().flatten();
+        arcs.chain(globals.map(|g| Work::Global(*g)))
+    }
+    /// Number of `(x, y)` pairs with at least one constraint
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Every binary constraint, with its name if it was given one
+    pub fn constraints(&self) -> impl Iterator<Item = (C, C, Option<&str>)> + '_ {
+        self.rules
+            .iter()
+            .flat_map(|((x, y), m)| m.0.iter().map(move |r| (*x, *y, r.1.as_deref())))
+    }
+
+    /// The classes of every global constraint
+    pub fn globals(&self) -> impl Iterator<Item = &[C]> + '_ {
+        self.globals.iter().map(|g| g.classes.as_slice())
+    }
+
+    /// Everything that touches `q` at all, for when its domain is replaced
+    fn work_touching(&self, q: C) -> impl Iterator<Item = Work<C>> + '_ {
+        let out = self.by_source.get(&q).into_iter().flatten();
+        self.work_for(q).chain(out.map(move |y| Work::Arc(q, *y)))
+    }
+}
+
+/// Classes `C`, each with a [`Domain`] of candidate values such as a `Vec` or [`BitDomain`].
+///
+/// Solving is incremental: domains, constraints and decisions can be added at
+/// any time, and [`AC3::propagate`] only revisits the work they affect.
+pub struct AC3<C: Class, D: Domain> {
+    data: HashMap<C, D>,
+    arcs: Arcs<C, D>,
+    pending: Agenda<Work<C>>,
+    pending_unary: HashSet<C>,
+    // the contradiction that stopped propagation part way, if any
+    failed: Option<SolveError<C, D>>,
+    // each class the first time it was given a domain, with that domain, so
+    // a restore can bring back the classes added after a snapshot
+    introduced: Vec<(C, D)>,
+}
+
+impl<C: Class, D: Domain> Default for AC3<C, D> {
+    fn default() -> Self {
+        AC3 {
+            data: HashMap::new(),
+            arcs: Arcs::default(),
+            pending: Agenda::default(),
+            pending_unary: HashSet::new(),
+            failed: None,
+            introduced: Vec::new(),
+        }
+    }
+}
+
+impl<C: Class, D: Domain> fmt::Debug for Arcs<C, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Arcs")
+            .field("arcs", &self.rules.len())
+            .field("constraints", &self.constraints().count())
+            .field("globals", &self.globals.len())
+            .field("unary", &self.unary.values().map(Vec::len).sum::<usize>())
+            .field("strategy", &self.strategy)
+            .finish()
+    }
+}
+
+impl<C: Class, D: Domain + fmt::Debug> fmt::Debug for AC3<C, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AC3")
+            .field("domains", &self.data)
+            .field("arcs", &self.arcs)
+            .field("pending", &self.pending.len())
+            .finish()
+    }
+}
+
+/// One line per class, sorted, with its domain and how many arcs lead into it
+impl<C: Class, D: Domain + fmt::Debug> fmt::Display for AC3<C, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lines = self
+            .data
+            .iter()
+            .map(|(class, domain)| {
+                let arcs = self.arcs.by_target.get(class).map(Vec::len).unwrap_or(0);
+                format!("{:?}: {:?} ({} arcs)", class, domain, arcs)
+            })
+            .collect::<Vec<_>>();
+        lines.sort();
+        write!(
+            f,
+            "{} classes, {} arcs, {} globals",
+            self.data.len(),
+            self.arcs.len(),
+            self.arcs.globals.len()
+        )?;
+        for line in lines {
+            write!(f, "\n  {}", line)?;
+        }
+        Ok(())
     }
 }
 
-#[derive(Default)]
-pub struct AC3<C: Class, T> {
-    data: HashMap<C, Vec<T>>,
-    arcs: Arcs<C, T>,
+/// The domains and outstanding work of an [`AC3`] at one point in time.
+///
+/// Constraints added after the snapshot are kept by [`AC3::restore`], and
+/// queued again so the next [`AC3::propagate`] applies them. So are classes
+/// first given a domain after the snapshot, which come back with the domain
+/// they were given; every other domain goes back to its value at the
+/// snapshot.
+#[derive(Clone)]
+pub struct Snapshot<C: Class, D> {
+    data: HashMap<C, D>,
+    pending: Agenda<Work<C>>,
+    pending_unary: HashSet<C>,
+    failed: Option<SolveError<C, D>>,
+    // how much of the constraint and class logs existed at the time
+    added: usize,
+    added_unary: usize,
+    introduced: usize,
 }
-impl<C: Class, T: 'static + PartialEq> AC3<C, T> {
+
+impl<C: Class, D: Domain + 'static> AC3<C, D>
+where
+    D::Value: Hash + Eq,
+{
+    /// Every class takes a different value
     pub fn apply_exclusivity(&mut self) {
         let keys = self.data.keys().cloned().collect::<Vec<_>>();
-        for x in &keys {
-            for y in &keys {
-                if x == y {
-                    continue;
+        self.add_all_different(&keys);
+    }
+
+    pub fn add_all_different(&mut self, classes: &[C]) {
+        self.add_global_constraint(classes, AllDifferent::rule())
+    }
+
+    /// Allow exactly the `(x_value, y_value)` pairs listed, in both directions
+    pub fn add_table_constraint<I>(&mut self, x: C, y: C, pairs: I)
+    where
+        I: IntoIterator<Item = (D::Value, D::Value)>,
+    {
+        let table = TableConstraint::new(pairs);
+        self.add_constraint(y, x, table.flipped());
+        self.add_constraint(x, y, table);
+    }
+}
+
+impl<C: Class, D> AC3<C, D>
+where
+    D: Domain + std::iter::FromIterator<<D as Domain>::Value> + 'static,
+{
+    /// One domain per class holding the candidates marked as allowed, each
+    /// once however often it is listed. A class that only appears with
+    /// disallowed candidates gets an empty domain.
+    pub fn from_candidates<I>(triples: I) -> AC3<C, D>
+    where
+        I: IntoIterator<Item = (C, D::Value, bool)>,
+    {
+        let mut candidates: HashMap<C, Vec<D::Value>> = HashMap::new();
+        for (class, value, allowed) in triples {
+            let values = candidates.entry(class).or_default();
+            if allowed && !values.contains(&value) {
+                values.push(value);
+            }
+        }
+        let mut ac3 = AC3::default();
+        for (class, values) in candidates {
+            ac3.add_domain(class, values.into_iter().collect());
+        }
+        ac3
+    }
+
+    /// Each row of `matrix` is one of `classes` and each column one of
+    /// `values`, with `true` where the class may take the value
+    pub fn from_matrix(
+        matrix: &FixedGrid<bool>,
+        classes: &[C],
+        values: &[D::Value],
+    ) -> anyhow::Result<AC3<C, D>> {
+        let width: usize = matrix.width();
+        // an empty grid has no width to divide its height by
+        let height = if width == 0 { 0 } else { matrix.height() };
+        if height != classes.len() || width != values.len() {
+            anyhow::bail!(
+                "{}x{} matrix does not fit {} classes by {} values",
+                height,
+                width,
+                classes.len(),
+                values.len()
+            );
+        }
+        let triples = classes.iter().enumerate().flat_map(|(y, class)| {
+            values.iter().enumerate().map(move |(x, value)| {
+                let allowed = matrix[Point::new(x as i64, y as i64)];
+                (*class, value.clone(), allowed)
+            })
+        });
+        Ok(AC3::from_candidates(triples))
+    }
+}
+
+/// The constraint that emptied a domain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cause<C> {
+    Unary(C),
+    Arc(C, C),
+    Global(Vec<C>),
+    /// A call to [`AC3::assign`] or [`AC3::remove_value`]
+    Decision(C),
+    /// The domain was already empty when it was given
+    EmptyDomain(C),
+}
+
+impl<C: fmt::Debug> fmt::Display for Cause<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Cause::Unary(c) => write!(f, "unary constraint on {:?}", c),
+            Cause::Arc(x, y) => write!(f, "arc ({:?}, {:?})", x, y),
+            Cause::Global(classes) => write!(f, "global constraint on {:?}", classes),
+            Cause::Decision(c) => write!(f, "decision on {:?}", c),
+            Cause::EmptyDomain(c) => write!(f, "empty domain given for {:?}", c),
+        }
+    }
+}
+
+/// Why [`AC3::solve`] could not produce a set of domains.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SolveError<C, D> {
+    /// A constraint refers to a class that was never given a domain
+    MissingDomain(C),
+    /// The problem has no solution: applying `cause` emptied the domain of `class`
+    Contradiction {
+        class: C,
+        cause: Cause<C>,
+        /// The values that were still in the domain before it emptied
+        removed: D,
+    },
+    /// Propagation finished but these classes still have more than one candidate
+    Ambiguous(Vec<(C, D)>),
+}
+
+impl<C: fmt::Debug, D: fmt::Debug> fmt::Display for SolveError<C, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolveError::MissingDomain(c) => write!(f, "no domain found for {:?}", c),
+            SolveError::Contradiction {
+                class,
+                cause,
+                removed,
+            } => write!(
+                f,
+                "domain of {:?} emptied by {}, last removed {:?}",
+                class, cause, removed
+            ),
+            SolveError::Ambiguous(open) => {
+                write!(f, "no unique assignment")?;
+                for (idx, (class, domain)) in open.iter().enumerate() {
+                    let sep = if idx == 0 { ":" } else { "," };
+                    write!(f, "{} {:?} could be {:?}", sep, class, domain)?;
                 }
-                self.add_constraint(*x, *y, MembershipExclusivity::rule())
+                Ok(())
             }
         }
     }
 }
 
-impl<C: Class, T: 'static> AC3<C, T> {
-    pub fn add_domain<D: Into<Vec<T>>>(&mut self, class: C, domain: D) {
-        let domain = domain.into();
+impl<C: fmt::Debug, D: fmt::Debug> std::error::Error for SolveError<C, D> {}
+
+impl<C: Class, D: Domain + 'static> AC3<C, D> {
+    /// Set the domain of `class`, replacing any it had before
+    pub fn add_domain(&mut self, class: C, domain: D) {
+        if !self.data.contains_key(&class) {
+            self.introduced.push((class, domain.clone()));
+        }
         self.data.insert(class, domain);
+        self.pending.extend(self.arcs.work_touching(class));
+        self.pending_unary.insert(class);
+        // the missing domain stopped propagation part way, so go over everything again
+        if let Some(SolveError::MissingDomain(missing)) = &self.failed {
+            if *missing == class {
+                self.failed = None;
+                self.pending.extend(self.arcs.all_work());
+                self.pending_unary.extend(self.arcs.unary.keys().cloned());
+            }
+        }
     }
 
-    pub fn add_constraint<R: Constraint<D = T> + 'static>(&mut self, x: C, y: C, rule: R) {
-        let r = Rule(Box::new(rule));
-        self.arcs.add_constraint(x, y, r)
+    /// Choose how arcs are revised from now on, see [`Strategy`]
+    pub fn set_strategy(&mut self, strategy: Strategy) {
+        self.arcs.strategy = strategy;
     }
 
-    pub fn solve(self) -> Result<HashMap<C, Vec<T>>> {
-        let AC3 { mut data, arcs } = self;
-        let mut agenda = Agenda::default();
-        agenda.extend(arcs.all_arcs());
+    pub fn add_constraint<R: Constraint<D = D> + 'static>(&mut self, x: C, y: C, rule: R) {
+        let r = Rule(Box::new(rule), None);
+        self.arcs.add_constraint(x, y, r);
+        self.pending.extend(std::iter::once(Work::Arc(x, y)));
+    }
 
-        while let Some((x, y)) = agenda.pop() {
-            log::trace!("Arc({:?}, {:?}) q={}", x, y, agenda.len());
-            let r = if let Some(r) = arcs.get(x, y) {
-                r
-            } else {
+    /// [`AC3::add_constraint`] with a name to show in [`AC3::to_dot`]
+    pub fn add_named_constraint<R: Constraint<D = D> + 'static>(
+        &mut self,
+        x: C,
+        y: C,
+        label: &str,
+        rule: R,
+    ) {
+        let r = Rule(Box::new(rule), Some(label.to_string()));
+        self.arcs.add_constraint(x, y, r);
+        self.pending.extend(std::iter::once(Work::Arc(x, y)));
+    }
+
+    /// Only keep the values of `class` that satisfy `f`.
+    ///
+    /// These are applied before any arcs are revised.
+    pub fn add_unary_constraint<F: Fn(&D::Value) -> bool + 'static>(&mut self, class: C, f: F) {
+        self.arcs.add_unary(class, Box::new(f));
+        self.pending_unary.insert(class);
+    }
+
+    /// Constrain several classes together, duplicate classes are ignored
+    pub fn add_global_constraint<R: GlobalConstraint<D = D> + 'static>(
+        &mut self,
+        classes: &[C],
+        rule: R,
+    ) {
+        let mut unique = Vec::with_capacity(classes.len());
+        for c in classes {
+            if !unique.contains(c) {
+                unique.push(*c);
+            }
+        }
+        let idx = self.arcs.add_global(unique, Box::new(rule));
+        self.pending.extend(std::iter::once(Work::Global(idx)));
+    }
+
+    /// Constrain `x` and `y` with `f(x_value, y_value)`, adding the arc in
+    /// both directions so the two can never disagree
+    pub fn add_binary_constraint<F>(&mut self, x: C, y: C, f: F)
+    where
+        F: Fn(&D::Value, &D::Value) -> bool + 'static,
+        D::Value: fmt::Debug,
+    {
+        self.add_shared_binary(x, y, Rc::new(f))
+    }
+
+    fn add_shared_binary<F>(&mut self, x: C, y: C, f: Rc<F>)
+    where
+        F: Fn(&D::Value, &D::Value) -> bool + 'static,
+        D::Value: fmt::Debug,
+    {
+        let forward = f.clone();
+        self.add_constraint(x, y, PairwiseConstraint::rule(move |a, b| forward(a, b)));
+        self.add_constraint(y, x, PairwiseConstraint::rule(move |b, a| f(a, b)));
+    }
+
+    /// Relate every pair of `classes` with [`AC3::add_binary_constraint`],
+    /// the earlier class in the slice is passed first
+    pub fn add_pairwise_constraint<F>(&mut self, classes: &[C], f: F)
+    where
+        F: Fn(&D::Value, &D::Value) -> bool + 'static,
+        D::Value: fmt::Debug,
+    {
+        let f = Rc::new(f);
+        for (i, x) in classes.iter().enumerate() {
+            for y in &classes[i + 1..] {
+                self.add_shared_binary(*x, *y, f.clone());
+            }
+        }
+    }
+
+    /// Add every `(x, y, relation)` with [`AC3::add_binary_constraint`].
+    ///
+    /// Every class mentioned must already have a domain, if one does not
+    /// then nothing is added.
+    pub fn with_relations<I, F>(mut self, relations: I) -> Result<Self, SolveError<C, D>>
+    where
+        I: IntoIterator<Item = (C, C, F)>,
+        F: Fn(&D::Value, &D::Value) -> bool + 'static,
+        D::Value: fmt::Debug,
+    {
+        let relations = relations.into_iter().collect::<Vec<_>>();
+        for (x, y, _) in &relations {
+            for class in [x, y].iter() {
+                if !self.data.contains_key(class) {
+                    return Err(SolveError::MissingDomain(**class));
+                }
+            }
+        }
+        for (x, y, f) in relations {
+            self.add_binary_constraint(x, y, f);
+        }
+        Ok(self)
+    }
+
+    /// The constraints registered so far
+    pub fn arcs(&self) -> &Arcs<C, D> {
+        &self.arcs
+    }
+
+    pub fn domains(&self) -> &HashMap<C, D> {
+        &self.data
+    }
+    pub fn domain(&self, class: C) -> Option<&D> {
+        self.data.get(&class)
+    }
+
+    /// Narrow `class` down to `value`. Call [`AC3::propagate`] to see the consequences.
+    pub fn assign(&mut self, class: C, value: &D::Value) -> Result<(), SolveError<C, D>> {
+        self.decide(class, |d| d.assign(value))
+    }
+
+    /// Rule `value` out for `class`. Call [`AC3::propagate`] to see the consequences.
+    pub fn remove_value(&mut self, class: C, value: &D::Value) -> Result<(), SolveError<C, D>> {
+        self.decide(class, |d| d.retain(|v| v != value))
+    }
+
+    fn decide<F: FnOnce(&mut D)>(&mut self, class: C, f: F) -> Result<(), SolveError<C, D>> {
+        let domain = self
+            .data
+            .get_mut(&class)
+            .ok_or(SolveError::MissingDomain(class))?;
+        let before = domain.clone();
+        f(domain);
+        if domain.is_empty() {
+            // nothing to undo later, the decision just doesn't happen
+            *domain = before.clone();
+            return Err(SolveError::Contradiction {
+                class,
+                cause: Cause::Decision(class),
+                removed: before,
+            });
+        }
+        if domain.len() < before.len() {
+            self.pending.extend(self.arcs.work_for(class));
+        }
+        Ok(())
+    }
+
+    /// Do all outstanding work, leaving every domain arc consistent.
+    ///
+    /// On a contradiction the domains are left part way through, and every
+    /// later call returns the same error until [`AC3::restore`]. Take a
+    /// [`AC3::snapshot`] first to be able to go back. A
+    /// [`SolveError::MissingDomain`] is cleared by adding that domain.
+    pub fn propagate(&mut self) -> Result<(), SolveError<C, D>> {
+        self.propagate_observed(&mut ())
+    }
+
+    fn propagate_observed<O: Observer<C, D>>(
+        &mut self,
+        obs: &mut O,
+    ) -> Result<(), SolveError<C, D>> {
+        if let Some(e) = &self.failed {
+            return Err(e.clone());
+        }
+        let mut agenda = std::mem::take(&mut self.pending);
+        let unary = std::mem::take(&mut self.pending_unary);
+        let result = node_consistency(
+            &self.arcs,
+            &mut self.data,
+            unary.into_iter(),
+            &mut agenda,
+            obs,
+        )
+        .and_then(|_| propagate(&self.arcs, &mut self.data, agenda, obs));
+        if let Err(e) = &result {
+            self.failed = Some(e.clone());
+        }
+        result
+    }
+
+    pub fn snapshot(&self) -> Snapshot<C, D> {
+        Snapshot {
+            data: self.data.clone(),
+            pending: self.pending.clone(),
+            pending_unary: self.pending_unary.clone(),
+            failed: self.failed.clone(),
+            added: self.arcs.added.len(),
+            added_unary: self.arcs.added_unary.len(),
+            introduced: self.introduced.len(),
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: Snapshot<C, D>) {
+        self.data = snapshot.data;
+        self.pending = snapshot.pending;
+        self.pending_unary = snapshot.pending_unary;
+        self.failed = snapshot.failed;
+        self.pending
+            .extend(self.arcs.added[snapshot.added..].iter().cloned());
+        self.pending_unary.extend(
+            self.arcs.added_unary[snapshot.added_unary..]
+                .iter()
+                .cloned(),
+        );
+        for (class, domain) in &self.introduced[snapshot.introduced..] {
+            if !self.data.contains_key(class) {
+                self.data.insert(*class, domain.clone());
+                self.pending.extend(self.arcs.work_touching(*class));
+                self.pending_unary.insert(*class);
+            }
+        }
+    }
+
+    /// Reduce every domain to arc consistency, failing as soon as one empties
+    pub fn solve(mut self) -> Result<HashMap<C, D>, SolveError<C, D>> {
+        self.propagate()?;
+        Ok(self.data)
+    }
+
+    /// [`AC3::solve`] down to a single value for every class, or
+    /// [`SolveError::Ambiguous`] with each class that has several left
+    pub fn solve_unique(self) -> Result<HashMap<C, D::Value>, SolveError<C, D>> {
+        let data = self.solve()?;
+        let open = data
+            .iter()
+            .filter(|(_, d)| d.len() > 1)
+            .map(|(c, d)| (*c, d.clone()))
+            .collect::<Vec<_>>();
+        if !open.is_empty() {
+            return Err(SolveError::Ambiguous(open));
+        }
+        // solve fails on any empty domain, so each one holds exactly one value
+        Ok(data
+            .into_iter()
+            .map(|(c, d)| (c, d.values().swap_remove(0)))
+            .collect())
+    }
+
+    /// Like [`AC3::solve`], also recording every value removed and why in `trace`
+    pub fn solve_with_trace(
+        mut self,
+        trace: &mut SolveTrace<C, D::Value>,
+    ) -> Result<HashMap<C, D>, SolveError<C, D>> {
+        self.propagate_observed(trace)?;
+        Ok(self.data)
+    }
+
+    /// Backtracking search that keeps the problem arc consistent after every assignment
+    pub fn search(self) -> Search<C, D> {
+        let AC3 { data, arcs, .. } = self;
+        Search {
+            arcs,
+            stack: vec![(data, None)],
+        }
+    }
+}
+
+/// Drop every value that fails a unary constraint on its class, and fail on
+/// any class that was given an empty domain
+fn node_consistency<C: Class, D: Domain, O: Observer<C, D>>(
+    arcs: &Arcs<C, D>,
+    data: &mut HashMap<C, D>,
+    classes: impl Iterator<Item = C>,
+    agenda: &mut Agenda<Work<C>>,
+    obs: &mut O,
+) -> Result<(), SolveError<C, D>> {
+    for class in classes {
+        if data.get(&class).map(|d| d.is_empty()).unwrap_or(false) {
+            return Err(SolveError::Contradiction {
+                class,
+                cause: Cause::EmptyDomain(class),
+                removed: D::default(),
+            });
+        }
+        let rules = match arcs.unary.get(&class) {
+            Some(r) => r,
+            None => continue,
+        };
+        let domain = data
+            .get_mut(&class)
+            .ok_or(SolveError::MissingDomain(class))?;
+        let before = domain.clone();
+        domain.retain(|v| rules.iter().all(|f| f(v)));
+        obs.revised(class, &before, domain, &Cause::Unary(class), &[]);
+        if domain.is_empty() {
+            return Err(SolveError::Contradiction {
+                class,
+                cause: Cause::Unary(class),
+                removed: before,
+            });
+        }
+        if domain.len() < before.len() {
+            agenda.extend(arcs.work_for(class));
+        }
+    }
+    Ok(())
+}
+
+fn propagate<C: Class, D: Domain + 'static, O: Observer<C, D>>(
+    arcs: &Arcs<C, D>,
+    data: &mut HashMap<C, D>,
+    mut agenda: Agenda<Work<C>>,
+    obs: &mut O,
+) -> Result<(), SolveError<C, D>> {
+    // the x domain before each revision, kept in one buffer so popping an
+    // arc does not allocate
+    let mut before = D::default();
+    while let Some(work) = agenda.pop() {
+        log::trace!("{:?} q={}", work, agenda.len());
+        match work {
+            Work::Arc(x, y) => {
+                let r = if let Some(r) = arcs.get(x, y) {
+                    r
+                } else {
+                    continue;
+                };
+                if !data.contains_key(&y) {
+                    return Err(SolveError::MissingDomain(y));
+                }
+                let (xdomain, ydomain) =
+                    get_mut_pair(data, &x, &y).ok_or(SolveError::MissingDomain(x))?;
+                before.clone_from(xdomain);
+                let revised = r.apply_with(arcs.strategy, xdomain, ydomain);
+                if revised || xdomain.is_empty() {
+                    let support = [(y, &*ydomain)];
+                    obs.revised(x, &before, xdomain, &Cause::Arc(x, y), &support);
+                }
+                if xdomain.is_empty() {
+                    return Err(SolveError::Contradiction {
+                        class: x,
+                        cause: Cause::Arc(x, y),
+                        removed: before,
+                    });
+                }
+                if revised {
+                    let before = agenda.len();
+                    agenda.extend(arcs.work_for(x));
+                    log::trace!("Q {} -> {}", before, agenda.len());
+                }
+            }
+            Work::Global(idx) => {
+                let global = &arcs.globals[idx];
+                if let Some(c) = global.classes.iter().find(|c| !data.contains_key(c)) {
+                    return Err(SolveError::MissingDomain(*c));
+                }
+                let mut domains = global
+                    .classes
+                    .iter()
+                    .map(|c| std::mem::take(data.get_mut(c).unwrap()))
+                    .collect::<Vec<_>>();
+                let before = domains.clone();
+                let revised = global.rule.apply(&mut domains);
+                for (c, d) in global.classes.iter().zip(domains) {
+                    data.insert(*c, d);
+                }
+                if !revised {
+                    continue;
+                }
+                obs.revised_global(&global.classes, &before, data);
+                for (c, old) in global.classes.iter().zip(before) {
+                    let now = data[c].len();
+                    if now == 0 {
+                        return Err(SolveError::Contradiction {
+                            class: *c,
+                            cause: Cause::Global(global.classes.clone()),
+                            removed: old,
+                        });
+                    }
+                    if now < old.len() {
+                        agenda.extend(arcs.work_for(*c).filter(|w| *w != work));
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Maintaining arc consistency search, created by [`AC3::search`].
+///
+/// Branches on the unassigned class with the smallest domain and runs the
+/// constraints again after each choice, backtracking when a domain empties.
+/// Iterating yields every complete assignment.
+pub struct Search<C: Class, D: Domain> {
+    arcs: Arcs<C, D>,
+    // domains still to explore, with the class that was just assigned
+    stack: Vec<(HashMap<C, D>, Option<C>)>,
+}
+
+impl<C: Class, D: Domain + 'static> Search<C, D> {
+    pub fn first_solution(mut self) -> Option<HashMap<C, D::Value>> {
+        self.next()
+    }
+
+    pub fn all_solutions(self) -> impl Iterator<Item = HashMap<C, D::Value>> {
+        self
+    }
+
+    pub fn count_solutions(self) -> usize {
+        self.count()
+    }
+}
+
+impl<C: Class, D: Domain + 'static> Iterator for Search<C, D> {
+    type Item = HashMap<C, D::Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((mut data, assigned)) = self.stack.pop() {
+            let mut agenda = Agenda::default();
+            let nodes = match assigned {
+                Some(c) => {
+                    agenda.extend(self.arcs.work_for(c));
+                    let classes = std::iter::once(c);
+                    node_consistency(&self.arcs, &mut data, classes, &mut agenda, &mut ())
+                }
+                None => {
+                    agenda.extend(self.arcs.all_work());
+                    let classes = self.arcs.unary.keys().cloned();
+                    node_consistency(&self.arcs, &mut data, classes, &mut agenda, &mut ())
+                }
+            };
+            if nodes.is_err() || propagate(&self.arcs, &mut data, agenda, &mut ()).is_err() {
+                log::trace!("backtrack after assigning {:?}", assigned);
                 continue;
+            }
+            if data.values().any(|d| d.is_empty()) {
+                continue;
+            }
+            // a value repeated in a domain is one choice, so branch on distinct values
+            let branch = data
+                .iter()
+                .filter(|(_, d)| d.len() > 1)
+                .map(|(c, d)| (*c, distinct_values(d)))
+                .filter(|(_, values)| values.len() > 1)
+                .min_by_key(|(_, values)| values.len());
+            let (class, values) = match branch {
+                Some(b) => b,
+                None => {
+                    return Some(
+                        data.into_iter()
+                            .map(|(c, d)| (c, d.values().swap_remove(0)))
+                            .collect(),
+                    )
+                }
             };
-            let (xdomain, ydomain) = get_mut_pair(&mut data, &x, &y)
-                .ok_or_else(|| anyhow::anyhow!("no domain found"))?;
-            let revised = r.apply(xdomain, ydomain.as_slice());
-            if revised {
-                // TODO this will result in duplicates
-                let before = agenda.len();
-                agenda.extend(arcs.arcs_for_y(x));
-                log::trace!("Q {} -> {}", before, agenda.len());
+            // pushed in reverse so the first value is explored first
+            for value in values.iter().rev() {
+                let mut child = data.clone();
+                child.get_mut(&class).unwrap().assign(value);
+                self.stack.push((child, Some(class)));
             }
         }
-        Ok(data)
+        None
     }
 }
 
+/// The values of `d` in order, each only once
+fn distinct_values<D: Domain>(d: &D) -> Vec<D::Value> {
+    let mut values: Vec<D::Value> = Vec::with_capacity(d.len());
+    d.for_each(|v| {
+        if !values.contains(v) {
+            values.push(v.clone());
+        }
+    });
+    values
+}
+
 fn get_mut_pair<'a, K, V>(
     conns: &'a mut HashMap<K, V>,
     a: &K,
@@ -138,18 +965,18 @@ where
     }
 }
 
-pub struct MultiConstraint<T>(Vec<Rule<T>>);
+pub struct MultiConstraint<D>(Vec<Rule<D>>);
 
-impl<T> Default for MultiConstraint<T> {
+impl<D> Default for MultiConstraint<D> {
     fn default() -> Self {
         MultiConstraint(Vec::new())
     }
 }
 
-impl<T: 'static> Constraint for MultiConstraint<T> {
-    type D = T;
+impl<D: Domain + 'static> Constraint for MultiConstraint<D> {
+    type D = D;
 
-    fn apply(&self, xdomain: &mut Vec<Self::D>, ydomain: &[Self::D]) -> bool {
+    fn apply(&self, xdomain: &mut D, ydomain: &D) -> bool {
         let mut modified = false;
         for r in &self.0 {
             modified = r.0.apply(xdomain, ydomain) || modified;
@@ -157,57 +984,77 @@ impl<T: 'static> Constraint for MultiConstraint<T> {
         log::trace!("multi_rule modified: {:?}", modified);
         modified
     }
+
+    fn apply_with(&self, strategy: Strategy, xdomain: &mut D, ydomain: &D) -> bool {
+        let mut modified = false;
+        for r in &self.0 {
+            modified = r.0.apply_with(strategy, xdomain, ydomain) || modified;
+        }
+        modified
+    }
 }
 
-pub struct PairwiseConstraint<T>(Box<dyn Fn(&T, &T) -> bool>);
+type Predicate<T> = dyn Fn(&T, &T) -> bool;
+type UnaryPredicate<T> = dyn Fn(&T) -> bool;
+
+pub struct PairwiseConstraint<D: Domain>(Box<Predicate<D::Value>>);
 
-impl<T: 'static + fmt::Debug> PairwiseConstraint<T> {
-    pub fn rule<F: 'static + Fn(&T, &T) -> bool>(f: F) -> PairwiseConstraint<T> {
+impl<D: Domain> PairwiseConstraint<D>
+where
+    D::Value: fmt::Debug,
+{
+    pub fn rule<F: 'static + Fn(&D::Value, &D::Value) -> bool>(f: F) -> PairwiseConstraint<D> {
         PairwiseConstraint(Box::new(f))
     }
 }
 
-impl<T: fmt::Debug> Constraint for PairwiseConstraint<T> {
-    type D = T;
+impl<D: Domain> Constraint for PairwiseConstraint<D>
+where
+    D::Value: fmt::Debug,
+{
+    type D = D;
 
-    fn apply(&self, xdomain: &mut Vec<Self::D>, ydomain: &[Self::D]) -> bool {
+    fn apply(&self, xdomain: &mut D, ydomain: &D) -> bool {
         let mut modified = false;
         xdomain.retain(|x_value| {
-            for y_value in ydomain {
+            let supported = ydomain.any(|y_value| {
                 let sat = (self.0)(x_value, y_value);
                 log::trace!("x: {:?}, y: {:?} -> {:?}", x_value, y_value, sat);
-                if sat {
-                    return true;
-                }
+                sat
+            });
+            if !supported {
+                log::trace!("x: {:?} no possible values in y", x_value);
+                modified = true;
             }
-            log::trace!("x: {:?} no possible values in y", x_value);
-            modified = true;
-            false
+            supported
         });
         log::trace!("modified: {:?}", modified);
         modified
     }
 }
 
-pub struct MembershipExclusivity<T>(PhantomData<T>);
+/// Removes a value from x once y is narrowed to exactly that value.
+///
+/// [`AllDifferent`] prunes much more for the same meaning.
+pub struct MembershipExclusivity<D>(PhantomData<D>);
 
-impl<T> MembershipExclusivity<T> {
-    fn rule() -> MembershipExclusivity<T> {
-        MembershipExclusivity(PhantomData::default())
+impl<D> MembershipExclusivity<D> {
+    pub fn rule() -> MembershipExclusivity<D> {
+        MembershipExclusivity(PhantomData)
     }
 }
 
-impl<T: PartialEq> Constraint for MembershipExclusivity<T> {
-    type D = T;
+impl<D: Domain> Constraint for MembershipExclusivity<D> {
+    type D = D;
 
-    fn apply(&self, xdomain: &mut Vec<Self::D>, ydomain: &[Self::D]) -> bool {
+    fn apply(&self, xdomain: &mut D, ydomain: &D) -> bool {
         if ydomain.len() != 1 {
             return false;
         }
-        let exclude = &ydomain[0];
         let mut modified = false;
         xdomain.retain(|x| {
-            if x == exclude {
+            // y holds exactly one value, so this is a single comparison
+            if ydomain.contains(x) {
                 modified = true;
                 false
             } else {
@@ -218,6 +1065,23 @@ impl<T: PartialEq> Constraint for MembershipExclusivity<T> {
     }
 }
 
+/// x and y take the same value, using [`Domain::intersect_with`].
+pub struct Equality<D>(PhantomData<D>);
+
+impl<D> Equality<D> {
+    pub fn rule() -> Equality<D> {
+        Equality(PhantomData)
+    }
+}
+
+impl<D: Domain> Constraint for Equality<D> {
+    type D = D;
+
+    fn apply(&self, xdomain: &mut D, ydomain: &D) -> bool {
+        xdomain.intersect_with(ydomain)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -240,4 +1104,502 @@ mod tests {
                 .collect()
         )
     }
+
+    #[test]
+    fn contradiction() {
+        let mut ac3 = AC3::default();
+        ac3.add_domain("A", vec![1, 2]);
+        ac3.add_domain("B", vec![2, 3]);
+        ac3.add_domain("C", vec![3]);
+        ac3.add_constraint("A", "B", PairwiseConstraint::rule(|a, b| a == b));
+        ac3.add_constraint("B", "C", PairwiseConstraint::rule(|b, c| b < c));
+        ac3.add_constraint("C", "A", PairwiseConstraint::rule(|c, a| c < a));
+        match ac3.solve() {
+            Err(SolveError::Contradiction {
+                class,
+                cause,
+                removed,
+            }) => {
+                assert_eq!(class, "C");
+                assert_eq!(cause, Cause::Arc("C", "A"));
+                assert_eq!(removed, vec![3]);
+            }
+            other => panic!("expected a contradiction, got {:?}", other),
+        }
+
+        let mut ac3 = AC3::default();
+        ac3.add_domain("A", vec![1]);
+        ac3.add_constraint("A", "B", PairwiseConstraint::rule(|a, b| a == b));
+        assert_eq!(ac3.solve(), Err(SolveError::MissingDomain("B")));
+
+        // no arcs needed to notice a domain that starts out empty
+        let mut ac3 = AC3::default();
+        ac3.add_domain("A", vec![1]);
+        ac3.add_domain("B", vec![]);
+        assert_eq!(
+            ac3.solve(),
+            Err(SolveError::Contradiction {
+                class: "B",
+                cause: Cause::EmptyDomain("B"),
+                removed: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn binary_constraints() {
+        let mut ac3 = AC3::default();
+        ac3.add_domain("A", vec![1, 2, 3]);
+        ac3.add_domain("B", vec![1, 2, 3]);
+        ac3.add_domain("C", vec![1, 2, 3]);
+        ac3.add_binary_constraint("A", "B", |a, b| a > b);
+        ac3.add_binary_constraint("B", "C", |b, c| b == c);
+        let data = ac3.solve().unwrap();
+        assert_eq!(
+            data,
+            vec![("A", vec![2, 3]), ("B", vec![1, 2]), ("C", vec![1, 2]),]
+                .into_iter()
+                .collect()
+        );
+
+        let mut ac3 = AC3::default();
+        for c in &["A", "B", "C", "D"] {
+            ac3.add_domain(*c, vec![1, 2, 3, 4]);
+        }
+        ac3.add_pairwise_constraint(&["A", "B", "C", "D"], |x, y| x < y);
+        let data = ac3.solve().unwrap();
+        assert_eq!(data["A"], vec![1]);
+        assert_eq!(data["C"], vec![3]);
+        assert_eq!(data["D"], vec![4]);
+    }
+
+    #[test]
+    fn search_classic() {
+        let mut ac3 = AC3::default();
+        ac3.add_domain("A", vec![1, 2, 3]);
+        ac3.add_domain("B", vec![1, 2, 3]);
+        ac3.add_domain("C", vec![1, 2, 3]);
+        ac3.add_constraint("A", "B", PairwiseConstraint::rule(|a, b| a > b));
+        ac3.add_constraint("B", "A", PairwiseConstraint::rule(|b, a| b < a));
+        ac3.add_constraint("B", "C", PairwiseConstraint::rule(|b, c| b == c));
+        ac3.add_constraint("C", "B", PairwiseConstraint::rule(|c, b| c == b));
+        let mut found = ac3
+            .search()
+            .all_solutions()
+            .map(|s| (s["A"], s["B"], s["C"]))
+            .collect::<Vec<_>>();
+        found.sort();
+        assert_eq!(found, vec![(2, 1, 1), (3, 1, 1), (3, 2, 2)]);
+    }
+
+    #[test]
+    fn search_repeated_values() {
+        let mut ac3 = AC3::default();
+        ac3.add_domain("A", vec![1, 1]);
+        assert_eq!(ac3.search().count_solutions(), 1);
+
+        let mut ac3 = AC3::default();
+        ac3.add_domain("A", vec![1, 2, 1, 2]);
+        ac3.add_domain("B", vec![2, 2, 3]);
+        ac3.add_constraint("A", "B", PairwiseConstraint::rule(|a, b| a != b));
+        ac3.add_constraint("B", "A", PairwiseConstraint::rule(|b, a| b != a));
+        let mut found = ac3
+            .search()
+            .all_solutions()
+            .map(|s| (s["A"], s["B"]))
+            .collect::<Vec<_>>();
+        found.sort();
+        assert_eq!(found, vec![(1, 2), (1, 3), (2, 3)]);
+    }
+
+    fn queens(n: i32) -> AC3<i32, Vec<i32>> {
+        let mut ac3 = AC3::default();
+        for col in 0..n {
+            ac3.add_domain(col, (0..n).collect::<Vec<_>>());
+        }
+        for x in 0..n {
+            for y in 0..n {
+                if x != y {
+                    let gap = (x - y).abs();
+                    ac3.add_constraint(
+                        x,
+                        y,
+                        PairwiseConstraint::rule(move |a: &i32, b: &i32| {
+                            a != b && (a - b).abs() != gap
+                        }),
+                    );
+                }
+            }
+        }
+        ac3
+    }
+
+    #[test]
+    fn search_queens() {
+        assert_eq!(queens(8).search().count_solutions(), 92);
+        assert!(queens(3).search().first_solution().is_none());
+
+        let board = queens(6).search().first_solution().unwrap();
+        for x in 0..6 {
+            for y in 0..x {
+                assert_ne!(board[&x], board[&y]);
+                assert_ne!((board[&x] - board[&y]).abs(), x - y);
+            }
+        }
+    }
+
+    #[test]
+    fn all_different() {
+        let mut ac3 = AC3::default();
+        for c in 0..4 {
+            ac3.add_domain(c, vec![1, 2, 3, 4]);
+        }
+        ac3.apply_exclusivity();
+        assert_eq!(ac3.search().count_solutions(), 24);
+
+        // the last class can only be 3 once the others have shared out 1 and 2,
+        // single value exclusion would not see that
+        let mut ac3 = AC3::default();
+        ac3.add_domain("A", vec![1, 2]);
+        ac3.add_domain("B", vec![1, 2]);
+        ac3.add_domain("C", vec![1, 2, 3]);
+        ac3.add_all_different(&["A", "B", "C"]);
+        ac3.add_binary_constraint("A", "B", |a, b| a < b);
+        let data = ac3.solve().unwrap();
+        assert_eq!(data["A"], vec![1]);
+        assert_eq!(data["B"], vec![2]);
+        assert_eq!(data["C"], vec![3]);
+
+        let mut ac3 = AC3::default();
+        for c in &["A", "B", "C"] {
+            ac3.add_domain(*c, vec![1, 2]);
+        }
+        ac3.apply_exclusivity();
+        match ac3.solve() {
+            Err(SolveError::Contradiction {
+                cause: Cause::Global(classes),
+                ..
+            }) => assert_eq!(classes.len(), 3),
+            other => panic!("expected a contradiction, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn unary_constraints() {
+        let mut ac3 = AC3::default();
+        for c in &["A", "B", "C"] {
+            ac3.add_domain(*c, vec![0, 1, 2]);
+        }
+        ac3.add_unary_constraint("A", |v| *v != 0);
+        ac3.add_unary_constraint("A", |v| *v != 1);
+        ac3.add_binary_constraint("A", "B", |a, b| a > b);
+        let data = ac3.solve().unwrap();
+        assert_eq!(data["A"], vec![2]);
+        assert_eq!(data["B"], vec![0, 1]);
+        assert_eq!(data["C"], vec![0, 1, 2]);
+
+        let mut ac3 = AC3::default();
+        for c in 0..3 {
+            ac3.add_domain(c, vec![0, 1, 2]);
+            ac3.add_unary_constraint(c, move |v| *v != c);
+        }
+        ac3.apply_exclusivity();
+        assert_eq!(ac3.search().count_solutions(), 2);
+
+        let mut ac3 = AC3::default();
+        ac3.add_domain("A", vec![1, 3]);
+        ac3.add_unary_constraint("A", |v| v % 2 == 0);
+        assert_eq!(
+            ac3.solve(),
+            Err(SolveError::Contradiction {
+                class: "A",
+                cause: Cause::Unary("A"),
+                removed: vec![1, 3],
+            })
+        );
+    }
+
+    #[test]
+    fn trace_explains() {
+        let mut ac3 = AC3::default();
+        ac3.add_domain("A", vec![1, 2, 3, 4]);
+        ac3.add_domain("B", vec![1, 2, 3, 4]);
+        ac3.add_domain("C", vec![1, 2, 3, 4]);
+        ac3.add_unary_constraint("A", |a| *a != 4);
+        ac3.add_binary_constraint("A", "B", |a, b| a > b);
+        ac3.add_binary_constraint("B", "C", |b, c| b > c);
+        let mut trace = SolveTrace::new();
+        let data = ac3.solve_with_trace(&mut trace).unwrap();
+        assert_eq!(data["C"], vec![1]);
+        assert_eq!(trace.removals().len(), 9);
+
+        // C can't be 2: B would have to be 3 or more, and A < 4 rules that out
+        let steps = trace.causes("C", &2).unwrap();
+        assert_eq!(steps.last().unwrap().class, "C");
+        assert_eq!(steps[0].cause, Cause::Unary("A"));
+        assert!(steps.iter().any(|r| r.class == "B" && r.value == 3));
+
+        let text = trace.explain("C", &2).unwrap();
+        assert!(text.starts_with("1. \"A\" != 4 by unary constraint on \"A\"\n"));
+        let last = text.lines().last().unwrap();
+        assert!(last.contains("\"C\" != 2 by arc (\"C\", \"B\"), given \"B\" in ["));
+        assert!(trace.explain("C", &1).is_none());
+    }
+
+    #[test]
+    fn bit_domains() {
+        let n = 8;
+        let mut ac3 = AC3::default();
+        for col in 0..n {
+            ac3.add_domain(col, BitDomain::full(n));
+        }
+        for x in 0..n {
+            for y in 0..x {
+                let gap = x - y;
+                ac3.add_binary_constraint(x, y, move |a: &usize, b: &usize| {
+                    a != b && (*a as i64 - *b as i64).unsigned_abs() as usize != gap
+                });
+            }
+        }
+        assert_eq!(ac3.search().count_solutions(), 92);
+
+        let build = || {
+            let mut ac3 = AC3::default();
+            ac3.add_domain("A", [1, 4, 5, 9].iter().cloned().collect::<BitDomain>());
+            ac3.add_domain("B", [0, 4, 9, 12].iter().cloned().collect::<BitDomain>());
+            ac3.add_constraint("A", "B", Equality::rule());
+            ac3.add_constraint("B", "A", Equality::rule());
+            ac3
+        };
+        let data = build().solve().unwrap();
+        assert_eq!(data["A"].values(), vec![4, 9]);
+        assert_eq!(data["B"], data["A"]);
+        let mut ac3 = build();
+        ac3.add_all_different(&["A", "B"]);
+        assert_eq!(ac3.search().count_solutions(), 0);
+    }
+
+    #[test]
+    fn incremental() {
+        let mut ac3 = AC3::default();
+        ac3.add_domain("A", vec![1, 2, 3]);
+        ac3.add_domain("B", vec![1, 2, 3]);
+        ac3.add_domain("C", vec![1, 2, 3]);
+        ac3.add_binary_constraint("A", "B", |a, b| a > b);
+        ac3.add_binary_constraint("B", "C", |b, c| b == c);
+        ac3.propagate().unwrap();
+        assert_eq!(ac3.domain("A"), Some(&vec![2, 3]));
+
+        let start = ac3.snapshot();
+        ac3.assign("A", &2).unwrap();
+        ac3.propagate().unwrap();
+        assert_eq!(ac3.domain("C"), Some(&vec![1]));
+
+        ac3.restore(start.clone());
+        assert_eq!(ac3.domain("C"), Some(&vec![1, 2]));
+        ac3.remove_value("B", &1).unwrap();
+        ac3.propagate().unwrap();
+        assert_eq!(ac3.domain("A"), Some(&vec![3]));
+        assert_eq!(ac3.domain("C"), Some(&vec![2]));
+
+        ac3.restore(start);
+        ac3.add_domain("D", vec![1, 2, 3, 4]);
+        ac3.add_binary_constraint("A", "D", |a, d| a == d);
+        ac3.propagate().unwrap();
+        assert_eq!(ac3.domain("D"), Some(&vec![2, 3]));
+        assert_eq!(ac3.domain("A"), Some(&vec![2, 3]));
+        assert!(ac3.assign("D", &4).is_err());
+        assert_eq!(ac3.domain("D"), Some(&vec![2, 3]));
+
+        // replacing a domain revisits the arcs leaving it as well as those into it
+        ac3.add_domain("D", vec![3]);
+        ac3.propagate().unwrap();
+        assert_eq!(ac3.domain("A"), Some(&vec![3]));
+
+        // constraints added after a snapshot are applied after restoring it
+        let late = ac3.snapshot();
+        ac3.add_unary_constraint("C", |c| *c != 2);
+        ac3.restore(late);
+        ac3.propagate().unwrap();
+        assert_eq!(ac3.domain("C"), Some(&vec![1]));
+        assert_eq!(ac3.domain("B"), Some(&vec![1]));
+
+        let mut ac3 = AC3::default();
+        ac3.add_domain("A", vec![1, 2, 3]);
+        ac3.add_domain("B", vec![1, 2, 3]);
+        let start = ac3.snapshot();
+        ac3.add_binary_constraint("A", "B", |a, b| a > b);
+        ac3.restore(start);
+        ac3.propagate().unwrap();
+        assert_eq!(ac3.domain("A"), Some(&vec![2, 3]));
+        assert_eq!(ac3.domain("B"), Some(&vec![1, 2]));
+
+        // a contradiction keeps failing until the next restore
+        let start = ac3.snapshot();
+        ac3.add_unary_constraint("B", |b| *b > 2);
+        assert!(matches!(
+            ac3.propagate(),
+            Err(SolveError::Contradiction { .. })
+        ));
+        assert!(ac3.propagate().is_err());
+        // the late constraint is kept, so the restored problem fails on its own
+        ac3.restore(start);
+        assert!(ac3.propagate().is_err());
+
+        let mut ac3 = AC3::default();
+        ac3.add_domain("A", vec![1]);
+        ac3.add_domain("B", vec![2]);
+        ac3.add_binary_constraint("A", "B", |a, b| a == b);
+        let first = ac3.propagate().unwrap_err();
+        assert_eq!(ac3.propagate(), Err(first));
+
+        // classes added after a snapshot survive a restore along with their constraints
+        let mut ac3 = AC3::default();
+        ac3.add_domain("A", vec![1, 2, 3]);
+        let start = ac3.snapshot();
+        ac3.add_domain("D", vec![2, 3, 4]);
+        ac3.add_binary_constraint("A", "D", |a, d| a == d);
+        ac3.assign("D", &3).unwrap();
+        ac3.propagate().unwrap();
+        ac3.restore(start);
+        ac3.propagate().unwrap();
+        assert_eq!(ac3.domain("A"), Some(&vec![2, 3]));
+        assert_eq!(ac3.domain("D"), Some(&vec![2, 3]));
+
+        // a missing domain is not sticky, giving it lets propagation finish
+        ac3.add_binary_constraint("E", "A", |e, a| e < a);
+        assert_eq!(ac3.propagate(), Err(SolveError::MissingDomain("E")));
+        ac3.add_domain("E", vec![1, 2, 3]);
+        ac3.propagate().unwrap();
+        assert_eq!(ac3.domain("E"), Some(&vec![1, 2]));
+    }
+
+    #[test]
+    fn long_chain() {
+        // 2 * 3000 arcs, a full scan per pop would take far too long
+        let n = 3000;
+        let mut ac3 = AC3::default();
+        ac3.add_domain(0, vec![1]);
+        for i in 1..n {
+            ac3.add_domain(i, vec![0, 1, 2]);
+            ac3.add_constraint(i, i - 1, PairwiseConstraint::rule(|a, b| a == b));
+            ac3.add_constraint(i - 1, i, PairwiseConstraint::rule(|a, b| a == b));
+        }
+        let data = ac3.solve().unwrap();
+        assert!(data.values().all(|d| d == &vec![1]));
+    }
+
+    #[test]
+    fn table_strategies() {
+        for strategy in [Strategy::Ac3, Strategy::Ac4, Strategy::Ac2001].iter() {
+            let mut ac3 = AC3::<usize, BitDomain>::default();
+            ac3.set_strategy(*strategy);
+            for col in 0..8 {
+                ac3.add_domain(col, BitDomain::full(8));
+            }
+            for x in 0..8 {
+                for y in x + 1..8 {
+                    let pairs = (0..8usize)
+                        .flat_map(|a| (0..8usize).map(move |b| (a, b)))
+                        .filter(|(a, b)| {
+                            a != b && (*a as isize - *b as isize).abs() != (y - x) as isize
+                        })
+                        .collect::<Vec<_>>();
+                    ac3.add_table_constraint(x, y, pairs);
+                }
+            }
+            assert_eq!(ac3.search().count_solutions(), 92, "{:?}", strategy);
+        }
+    }
+
+    #[test]
+    fn from_tables() {
+        let triples = vec![
+            ("A", 1, true),
+            ("A", 2, true),
+            ("A", 3, true),
+            ("B", 1, true),
+            ("B", 2, true),
+            ("B", 4, false),
+            ("C", 2, true),
+        ];
+        let relations: Vec<(_, _, Box<Predicate<i32>>)> = vec![
+            ("A", "B", Box::new(|a, b| a > b)),
+            ("B", "C", Box::new(|b, c| b == c)),
+        ];
+        let data = AC3::<_, Vec<i32>>::from_candidates(triples)
+            .with_relations(relations)
+            .unwrap()
+            .solve()
+            .unwrap();
+        assert_eq!(data["A"], vec![3]);
+        assert_eq!(data["B"], vec![2]);
+
+        let missing = AC3::<_, Vec<i32>>::from_candidates(vec![("A", 1, true), ("B", 1, false)])
+            .with_relations(vec![("B", "C", |b: &i32, c: &i32| b == c)]);
+        assert_eq!(missing.err(), Some(SolveError::MissingDomain("C")));
+
+        // the same candidate listed again, as when it shows up in several foods
+        let repeated = vec![("A", 1, true), ("A", 1, true), ("B", 2, true)];
+        let ac3 = AC3::<_, Vec<i32>>::from_candidates(repeated.clone());
+        assert_eq!(ac3.domain("A"), Some(&vec![1]));
+        assert_eq!(
+            ac3.solve_unique().unwrap(),
+            vec![("A", 1), ("B", 2)].into_iter().collect()
+        );
+        let ac3 = AC3::<_, Vec<i32>>::from_candidates(repeated);
+        assert_eq!(ac3.search().count_solutions(), 1);
+    }
+
+    #[test]
+    fn from_matrix() {
+        let matrix = FixedGrid::parse_ascii_grid("#.#\n.##\n", |c| Ok(c == '#')).unwrap();
+        let ac3 = AC3::<_, BitDomain>::from_matrix(&matrix, &['x', 'y'], &[0, 1, 2]).unwrap();
+        assert_eq!(ac3.domain('x').unwrap().values(), vec![0, 2]);
+        assert_eq!(ac3.domain('y').unwrap().values(), vec![1, 2]);
+        assert!(AC3::<_, BitDomain>::from_matrix(&matrix, &['x'], &[0, 1, 2]).is_err());
+
+        let empty = FixedGrid::parse_ascii_grid("", |c| Ok(c == '#')).unwrap();
+        assert!(AC3::<_, BitDomain>::from_matrix(&empty, &['x'], &[0]).is_err());
+        let ac3 = AC3::<char, BitDomain>::from_matrix(&empty, &[], &[]).unwrap();
+        assert_eq!(ac3.arcs().len(), 0);
+    }
+
+    #[test]
+    fn solve_unique() {
+        let mut ac3 = AC3::default();
+        ac3.add_domain("A", vec![1, 2, 3]);
+        ac3.add_domain("B", vec![1, 2]);
+        ac3.add_domain("C", vec![1]);
+        ac3.apply_exclusivity();
+        let assignment = ac3.solve_unique().unwrap();
+        assert_eq!(
+            assignment,
+            vec![("A", 3), ("B", 2), ("C", 1)].into_iter().collect()
+        );
+
+        let mut ac3 = AC3::default();
+        ac3.add_domain("A", vec![1, 2]);
+        ac3.add_domain("B", vec![1, 2]);
+        ac3.add_domain("C", vec![3]);
+        ac3.apply_exclusivity();
+        match ac3.solve_unique() {
+            Err(SolveError::Ambiguous(mut open)) => {
+                open.sort();
+                assert_eq!(open, vec![("A", vec![1, 2]), ("B", vec![1, 2])]);
+            }
+            other => panic!("expected an ambiguous result, got {:?}", other),
+        }
+
+        // an empty domain is reported by solve before any value is read
+        let ac3 = AC3::<_, Vec<i32>>::from_candidates(vec![("A", 1, false), ("B", 2, true)]);
+        assert_eq!(
+            ac3.solve_unique(),
+            Err(SolveError::Contradiction {
+                class: "A",
+                cause: Cause::EmptyDomain("A"),
+                removed: vec![],
+            })
+        );
+    }
 }
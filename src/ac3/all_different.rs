@@ -0,0 +1,107 @@
+use super::{Domain, GlobalConstraint};
+use crate::matching::{alternating_edges, hopcroft_karp};
+use std::{collections::HashMap, hash::Hash, marker::PhantomData};
+
+/// Every class takes a different value.
+///
+/// Uses Régin's matching based filtering: a value is kept only if some
+/// assignment of distinct values to all the classes uses it. This covers
+/// naked pairs and triples, Hall sets, and pigeonhole failures in one pass.
+/// When there is no such assignment at all, the domain of a class that could
+/// not be matched is emptied.
+pub struct AllDifferent<D>(PhantomData<D>);
+
+impl<D> AllDifferent<D> {
+    pub fn rule() -> AllDifferent<D> {
+        AllDifferent(PhantomData)
+    }
+}
+
+impl<D: Domain> GlobalConstraint for AllDifferent<D>
+where
+    D::Value: Hash + Eq,
+{
+    type D = D;
+
+    fn apply(&self, domains: &mut [D]) -> bool {
+        let mut ids = HashMap::new();
+        let adj = domains
+            .iter()
+            .map(|d| {
+                d.values()
+                    .into_iter()
+                    .map(|v| {
+                        let next = ids.len();
+                        *ids.entry(v).or_insert(next)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let m = ids.len();
+
+        let (var_match, val_match) = hopcroft_karp(&adj, m);
+        let var_match = match var_match.iter().position(|v| v.is_none()) {
+            Some(x) => {
+                log::trace!("all different: no matching for class {}", x);
+                domains[x].retain(|_| false);
+                return true;
+            }
+            None => var_match.into_iter().flatten().collect::<Vec<_>>(),
+        };
+        let alternates = alternating_edges(&adj, m, &var_match, &val_match);
+
+        let mut modified = false;
+        for (x, domain) in domains.iter_mut().enumerate() {
+            domain.retain(|value| {
+                let v = ids[value];
+                let edge = adj[x].iter().position(|e| *e == v).unwrap();
+                let keep = var_match[x] == v || alternates[x][edge];
+                modified |= !keep;
+                keep
+            });
+        }
+        modified
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prune(domains: &[&[i32]]) -> Vec<Vec<i32>> {
+        let mut domains = domains.iter().map(|d| d.to_vec()).collect::<Vec<_>>();
+        AllDifferent::<Vec<i32>>::rule().apply(&mut domains);
+        domains
+    }
+
+    #[test]
+    fn naked_pair() {
+        assert_eq!(
+            prune(&[&[1, 2], &[1, 2], &[1, 2, 3, 4]]),
+            vec![vec![1, 2], vec![1, 2], vec![3, 4]]
+        );
+    }
+
+    #[test]
+    fn hall_interval() {
+        // {1,2,3} is used up by the first three, which leaves 4 for the last
+        // and 5 for the fourth, though nothing started as a singleton
+        assert_eq!(
+            prune(&[&[1, 2], &[2, 3], &[1, 3], &[1, 2, 3, 4, 5], &[3, 4]]),
+            vec![vec![1, 2], vec![2, 3], vec![1, 3], vec![5], vec![4]]
+        );
+    }
+
+    #[test]
+    fn pigeonhole() {
+        let pruned = prune(&[&[1, 2], &[1, 2], &[2, 1]]);
+        assert!(pruned.iter().any(|d| d.is_empty()));
+    }
+
+    #[test]
+    fn nothing_to_do() {
+        let mut domains = vec![vec![1, 2, 3], vec![1, 2, 3], vec![1, 2, 3]];
+        assert!(!AllDifferent::<Vec<i32>>::rule().apply(&mut domains));
+        assert_eq!(domains, vec![vec![1, 2, 3]; 3]);
+    }
+}
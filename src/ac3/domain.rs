@@ -0,0 +1,279 @@
+use crate::bitset::alphabet::{Key, KeySet};
+use std::fmt;
+
+/// The values a class may still take.
+///
+/// Constraints only ever take values away, so a domain needs little more
+/// than iteration and `retain`. Bit set domains override the bulk
+/// operations with single word arithmetic.
+pub trait Domain: Clone + Default {
+    type Value: Clone + PartialEq;
+
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    fn contains(&self, value: &Self::Value) -> bool;
+    fn values(&self) -> Vec<Self::Value>;
+    fn retain<F: FnMut(&Self::Value) -> bool>(&mut self, f: F);
+
+    /// Call `f` on every value. The default goes through [`values`](Domain::values);
+    /// the domains here override it to borrow instead.
+    fn for_each<F: FnMut(&Self::Value)>(&self, f: F) {
+        self.values().iter().for_each(f)
+    }
+
+    /// True if `f` holds for some value, stopping at the first that does
+    fn any<F: FnMut(&Self::Value) -> bool>(&self, f: F) -> bool {
+        self.values().iter().any(f)
+    }
+
+    /// Remove every value not in `other`, returning true if anything went
+    fn intersect_with(&mut self, other: &Self) -> bool {
+        let before = self.len();
+        self.retain(|v| other.contains(v));
+        self.len() != before
+    }
+
+    /// Narrow down to just `value`
+    fn assign(&mut self, value: &Self::Value) {
+        self.retain(|v| v == value)
+    }
+
+    /// If every value here was also in `before`, call `f` on each value of
+    /// `before` that has gone since and return true. Otherwise the domain
+    /// has grown back, and this returns false without calling `f`.
+    fn removed_since<F: FnMut(&Self::Value)>(&self, before: &Self, mut f: F) -> bool {
+        if self.any(|v| !before.contains(v)) {
+            return false;
+        }
+        before.for_each(|v| {
+            if !self.contains(v) {
+                f(v)
+            }
+        });
+        true
+    }
+}
+
+impl<T: Clone + PartialEq> Domain for Vec<T> {
+    type Value = T;
+
+    fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+    fn contains(&self, value: &T) -> bool {
+        self.as_slice().contains(value)
+    }
+    fn values(&self) -> Vec<T> {
+        self.clone()
+    }
+    fn retain<F: FnMut(&T) -> bool>(&mut self, f: F) {
+        Vec::retain(self, f)
+    }
+    fn for_each<F: FnMut(&T)>(&self, f: F) {
+        self.iter().for_each(f)
+    }
+    fn any<F: FnMut(&T) -> bool>(&self, f: F) -> bool {
+        self.iter().any(f)
+    }
+    fn removed_since<F: FnMut(&T)>(&self, before: &Self, mut f: F) -> bool {
+        // retain keeps the order, so a domain that only shrank is `before`
+        // with gaps, and one walk along both finds what went
+        let mut rest = self.iter().peekable();
+        for v in before {
+            if rest.peek() == Some(&v) {
+                rest.next();
+            }
+        }
+        if rest.peek().is_some() {
+            // grown back or reordered, so compare value by value
+            if self.iter().any(|v| !before.contains(v)) {
+                return false;
+            }
+            before.iter().filter(|v| !self.contains(v)).for_each(f);
+            return true;
+        }
+        let mut rest = self.iter().peekable();
+        for v in before {
+            if rest.peek() == Some(&v) {
+                rest.next();
+            } else {
+                f(v);
+            }
+        }
+        true
+    }
+}
+
+impl Domain for KeySet {
+    type Value = Key;
+
+    fn len(&self) -> usize {
+        KeySet::len(self)
+    }
+    fn contains(&self, value: &Key) -> bool {
+        KeySet::contains(*self, *value)
+    }
+    fn values(&self) -> Vec<Key> {
+        self.iter().collect()
+    }
+    fn retain<F: FnMut(&Key) -> bool>(&mut self, mut f: F) {
+        *self = self
+            .iter()
+            .filter(|k| f(k))
+            .fold(KeySet::new(), |s, k| s.insert(k));
+    }
+    fn for_each<F: FnMut(&Key)>(&self, mut f: F) {
+        self.iter().for_each(|k| f(&k))
+    }
+    fn any<F: FnMut(&Key) -> bool>(&self, mut f: F) -> bool {
+        self.iter().any(|k| f(&k))
+    }
+    fn intersect_with(&mut self, other: &Self) -> bool {
+        let before = *self;
+        *self = self.intersect(*other);
+        *self != before
+    }
+}
+
+/// A domain of small integers, `0..128`, as a bit mask.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct BitDomain(u128);
+
+impl BitDomain {
+    pub fn new() -> BitDomain {
+        BitDomain(0)
+    }
+
+    /// Every value in `0..n`
+    pub fn full(n: usize) -> BitDomain {
+        assert!(n <= 128);
+        BitDomain(u128::MAX.checked_shr(128 - n as u32).unwrap_or(0))
+    }
+
+    pub fn insert(&mut self, value: usize) {
+        assert!(value < 128, "BitDomain values must be below 128");
+        self.0 |= 1 << value;
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = usize> {
+        (0..128).filter(move |v| self.0 & (1 << v) != 0)
+    }
+}
+
+impl std::iter::FromIterator<usize> for BitDomain {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut d = BitDomain::new();
+        for v in iter {
+            d.insert(v);
+        }
+        d
+    }
+}
+
+impl fmt::Debug for BitDomain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl Domain for BitDomain {
+    type Value = usize;
+
+    fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+    fn contains(&self, value: &usize) -> bool {
+        *value < 128 && self.0 & (1 << value) != 0
+    }
+    fn values(&self) -> Vec<usize> {
+        self.iter().collect()
+    }
+    fn retain<F: FnMut(&usize) -> bool>(&mut self, mut f: F) {
+        self.0 = self.iter().filter(|v| f(v)).fold(0, |m, v| m | 1 << v);
+    }
+    fn for_each<F: FnMut(&usize)>(&self, mut f: F) {
+        self.iter().for_each(|v| f(&v))
+    }
+    fn any<F: FnMut(&usize) -> bool>(&self, mut f: F) -> bool {
+        self.iter().any(|v| f(&v))
+    }
+    fn intersect_with(&mut self, other: &Self) -> bool {
+        let before = self.0;
+        self.0 &= other.0;
+        self.0 != before
+    }
+    fn assign(&mut self, value: &usize) {
+        self.0 &= 1u128.checked_shl(*value as u32).unwrap_or(0);
+    }
+    fn removed_since<F: FnMut(&usize)>(&self, before: &Self, f: F) -> bool {
+        if self.0 & !before.0 != 0 {
+            return false;
+        }
+        BitDomain(before.0 & !self.0).for_each(f);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bit_domain() {
+        let mut d = BitDomain::full(10);
+        assert_eq!(Domain::len(&d), 10);
+        assert_eq!(BitDomain::full(128).len(), 128);
+        assert!(BitDomain::full(0).is_empty());
+        d.retain(|v| v % 3 == 0);
+        assert_eq!(d.values(), vec![0, 3, 6, 9]);
+        assert!(d.any(|v| *v == 6));
+        assert!(!d.any(|v| *v == 7));
+        let mut sum = 0;
+        d.for_each(|v| sum += v);
+        assert_eq!(sum, 18);
+        let other = [3, 9, 100].iter().cloned().collect::<BitDomain>();
+        assert!(d.intersect_with(&other));
+        assert!(!d.intersect_with(&other));
+        assert_eq!(format!("{:?}", d), "{3, 9}");
+        d.assign(&9);
+        assert_eq!(d.values(), vec![9]);
+        d.assign(&3);
+        assert!(d.is_empty());
+
+        let before = BitDomain::full(6);
+        let mut gone = vec![];
+        let after = [1, 4].iter().cloned().collect::<BitDomain>();
+        assert!(after.removed_since(&before, |v| gone.push(*v)));
+        assert_eq!(gone, vec![0, 2, 3, 5]);
+        assert!(!before.removed_since(&after, |_| panic!("grown back")));
+    }
+
+    #[test]
+    fn vec_removed_since() {
+        let before = vec![5, 1, 4, 2, 3];
+        let mut gone = vec![];
+        assert!(vec![1, 2].removed_since(&before, |v| gone.push(*v)));
+        assert_eq!(gone, vec![5, 4, 3]);
+        // reordered but not grown still reports the removals
+        gone.clear();
+        assert!(vec![2, 1].removed_since(&before, |v| gone.push(*v)));
+        assert_eq!(gone, vec![5, 4, 3]);
+        assert!(!vec![1, 6].removed_since(&before, |_| panic!("grown back")));
+    }
+
+    #[test]
+    fn key_set_domain() {
+        let mut d = "abcxyz"
+            .chars()
+            .fold(KeySet::new(), |s, c| s.insert(Key::from(c)));
+        d.retain(|k| char::from(*k) > 'b');
+        assert_eq!(format!("{:?}", d), "KeySet(cxyz)");
+        let vowels = "aeiouy"
+            .chars()
+            .fold(KeySet::new(), |s, c| s.insert(Key::from(c)));
+        assert!(d.intersect_with(&vowels));
+        assert_eq!(d.values(), vec![Key::from('y')]);
+    }
+}
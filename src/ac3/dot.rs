@@ -0,0 +1,105 @@
+use super::{Class, Domain, AC3};
+use std::{collections::HashMap, fmt::Write};
+
+impl<C: Class, D: Domain> AC3<C, D> {
+    /// The constraint graph in Graphviz DOT format.
+    ///
+    /// Each class is a node labelled with its domain size, each arc an edge
+    /// labelled with the names of its constraints, and each global constraint
+    /// a box joined to its classes.
+    pub fn to_dot(&self) -> String {
+        let mut classes = self.data.keys().cloned().collect::<Vec<_>>();
+        for (x, y, _) in self.arcs.constraints() {
+            classes.extend([x, y].iter());
+        }
+        for g in self.arcs.globals() {
+            classes.extend(g.iter());
+        }
+        classes.sort_by_key(|c| format!("{:?}", c));
+        classes.dedup();
+        let ids = classes
+            .iter()
+            .enumerate()
+            .map(|(idx, c)| (*c, idx))
+            .collect::<HashMap<_, _>>();
+
+        let mut out = String::from("digraph ac3 {\n");
+        for (idx, class) in classes.iter().enumerate() {
+            let size = match self.data.get(class) {
+                Some(d) => d.len().to_string(),
+                None => "?".to_string(),
+            };
+            let label = escape(&format!("{:?} ({})", class, size));
+            writeln!(out, "  n{} [label=\"{}\"];", idx, label).unwrap();
+        }
+
+        let mut edges: HashMap<(usize, usize), Vec<&str>> = HashMap::new();
+        for (x, y, label) in self.arcs.constraints() {
+            let names = edges.entry((ids[&x], ids[&y])).or_default();
+            names.extend(label);
+        }
+        let mut edges = edges.into_iter().collect::<Vec<_>>();
+        edges.sort();
+        for ((x, y), names) in edges {
+            if names.is_empty() {
+                writeln!(out, "  n{} -> n{};", x, y).unwrap();
+            } else {
+                let label = escape(&names.join(", "));
+                writeln!(out, "  n{} -> n{} [label=\"{}\"];", x, y, label).unwrap();
+            }
+        }
+
+        for (idx, g) in self.arcs.globals().enumerate() {
+            writeln!(out, "  g{} [shape=box, label=\"global {}\"];", idx, idx).unwrap();
+            for class in g {
+                writeln!(out, "  g{} -> n{} [dir=none];", idx, ids[class]).unwrap();
+            }
+        }
+        out.push('}');
+        out.push('\n');
+        out
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::PairwiseConstraint;
+    use super::*;
+
+    #[test]
+    fn dot_export() {
+        let mut ac3 = AC3::default();
+        ac3.add_domain("A", vec![1, 2, 3]);
+        ac3.add_domain("B", vec![1, 2]);
+        ac3.add_named_constraint("A", "B", "a > b", PairwiseConstraint::rule(|a, b| a > b));
+        ac3.add_constraint("B", "A", PairwiseConstraint::rule(|b, a| b < a));
+        ac3.add_all_different(&["A", "B", "C"]);
+        assert_eq!(
+            ac3.to_dot(),
+            [
+                "digraph ac3 {",
+                "  n0 [label=\"\\\"A\\\" (3)\"];",
+                "  n1 [label=\"\\\"B\\\" (2)\"];",
+                "  n2 [label=\"\\\"C\\\" (?)\"];",
+                "  n0 -> n1 [label=\"a > b\"];",
+                "  n1 -> n0;",
+                "  g0 [shape=box, label=\"global 0\"];",
+                "  g0 -> n0 [dir=none];",
+                "  g0 -> n1 [dir=none];",
+                "  g0 -> n2 [dir=none];",
+                "}",
+                "",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            ac3.to_string(),
+            "2 classes, 2 arcs, 1 globals\n  \"A\": [1, 2, 3] (1 arcs)\n  \"B\": [1, 2] (1 arcs)"
+        );
+        assert_eq!(ac3.arcs().constraints().count(), 2);
+    }
+}
@@ -0,0 +1,179 @@
+use super::{Constraint, Domain, Strategy};
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
+
+/// A relation given as the list of allowed `(x, y)` pairs.
+///
+/// With [`Strategy::Ac4`] each x value keeps a count of its remaining
+/// supports, updated from the y values removed since the last revision as
+/// found by [`Domain::removed_since`].
+/// With [`Strategy::Ac2001`] each x value remembers the last support it
+/// found and only searches on from there when that one is gone. Either way
+/// the bookkeeping is repaired if a domain grows back, so it is safe to use
+/// during search and after [`AC3::restore`](super::AC3::restore).
+pub struct TableConstraint<D: Domain> {
+    supports: HashMap<D::Value, Vec<D::Value>>,
+    // y value -> the x values it supports
+    supported: HashMap<D::Value, Vec<D::Value>>,
+    counters: RefCell<Option<Counters<D>>>,
+    last: RefCell<HashMap<D::Value, usize>>,
+}
+
+struct Counters<D: Domain> {
+    // y as it was at the last revision
+    seen_y: D,
+    count: HashMap<D::Value, usize>,
+}
+
+impl<D: Domain> TableConstraint<D>
+where
+    D::Value: Hash + Eq,
+{
+    pub fn new<I: IntoIterator<Item = (D::Value, D::Value)>>(pairs: I) -> TableConstraint<D> {
+        let mut supports: HashMap<D::Value, Vec<D::Value>> = HashMap::new();
+        let mut supported: HashMap<D::Value, Vec<D::Value>> = HashMap::new();
+        let unique = pairs.into_iter().collect::<HashSet<_>>();
+        for (x, y) in unique {
+            supports.entry(x.clone()).or_default().push(y.clone());
+            supported.entry(y).or_default().push(x);
+        }
+        TableConstraint {
+            supports,
+            supported,
+            counters: RefCell::new(None),
+            last: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// The same relation seen from y, for the reverse arc
+    pub fn flipped(&self) -> TableConstraint<D> {
+        let pairs = self
+            .supports
+            .iter()
+            .flat_map(|(x, ys)| ys.iter().map(move |y| (y.clone(), x.clone())));
+        TableConstraint::new(pairs)
+    }
+
+    fn supported_by(&self, x: &D::Value, ydomain: &D) -> bool {
+        self.supports
+            .get(x)
+            .map(|ys| ys.iter().any(|y| ydomain.contains(y)))
+            .unwrap_or(false)
+    }
+
+    fn revise_ac3(&self, xdomain: &mut D, ydomain: &D) -> bool {
+        let before = xdomain.len();
+        xdomain.retain(|x| self.supported_by(x, ydomain));
+        xdomain.len() != before
+    }
+
+    fn revise_ac4(&self, xdomain: &mut D, ydomain: &D) -> bool {
+        let mut counters = self.counters.borrow_mut();
+        let updated = match counters.as_mut() {
+            Some(Counters { seen_y, count }) => {
+                let supported = &self.supported;
+                let shrunk = ydomain.removed_since(seen_y, |gone| {
+                    for x in supported.get(gone).into_iter().flatten() {
+                        *count.get_mut(x).unwrap() -= 1;
+                    }
+                });
+                if shrunk {
+                    seen_y.clone_from(ydomain);
+                }
+                shrunk
+            }
+            None => false,
+        };
+        // first revision, or y grew back: count from scratch
+        if !updated {
+            let count = self
+                .supports
+                .iter()
+                .map(|(x, ys)| (x.clone(), ys.iter().filter(|y| ydomain.contains(y)).count()))
+                .collect();
+            *counters = Some(Counters {
+                seen_y: ydomain.clone(),
+                count,
+            });
+        }
+
+        let count = &counters.as_ref().unwrap().count;
+        let before = xdomain.len();
+        xdomain.retain(|x| count.get(x).map(|n| *n > 0).unwrap_or(false));
+        xdomain.len() != before
+    }
+
+    fn revise_ac2001(&self, xdomain: &mut D, ydomain: &D) -> bool {
+        let mut last = self.last.borrow_mut();
+        let before = xdomain.len();
+        xdomain.retain(|x| {
+            let ys = match self.supports.get(x) {
+                Some(ys) => ys,
+                None => return false,
+            };
+            let start = last.get(x).cloned().unwrap_or(0);
+            // carry on from the last support, wrapping round in case values came back
+            let found = (start..ys.len())
+                .chain(0..start)
+                .find(|idx| ydomain.contains(&ys[*idx]));
+            match found {
+                Some(idx) => {
+                    last.insert(x.clone(), idx);
+                    true
+                }
+                None => false,
+            }
+        });
+        xdomain.len() != before
+    }
+}
+
+impl<D: Domain> Constraint for TableConstraint<D>
+where
+    D::Value: Hash + Eq,
+{
+    type D = D;
+
+    fn apply(&self, xdomain: &mut D, ydomain: &D) -> bool {
+        self.revise_ac3(xdomain, ydomain)
+    }
+
+    fn apply_with(&self, strategy: Strategy, xdomain: &mut D, ydomain: &D) -> bool {
+        match strategy {
+            Strategy::Ac3 => self.revise_ac3(xdomain, ydomain),
+            Strategy::Ac4 => self.revise_ac4(xdomain, ydomain),
+            Strategy::Ac2001 => self.revise_ac2001(xdomain, ydomain),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strategies_agree() {
+        let pairs = vec![(1, 2), (1, 3), (2, 3), (3, 1), (4, 4)];
+        for strategy in [Strategy::Ac3, Strategy::Ac4, Strategy::Ac2001].iter() {
+            let table = TableConstraint::<Vec<i32>>::new(pairs.clone());
+            let mut x = vec![1, 2, 3, 4, 5];
+            assert!(table.apply_with(*strategy, &mut x, &vec![1, 2, 3]));
+            assert_eq!(x, vec![1, 2, 3]);
+            assert!(table.apply_with(*strategy, &mut x, &vec![1, 2]));
+            assert_eq!(x, vec![1, 3]);
+            assert!(!table.apply_with(*strategy, &mut x, &vec![1, 2]));
+            // y growing back again must not leave stale bookkeeping behind
+            let mut x = vec![1, 2, 3, 4];
+            assert!(table.apply_with(*strategy, &mut x, &vec![3, 4]));
+            assert_eq!(x, vec![1, 2, 4]);
+        }
+
+        let flipped = TableConstraint::<Vec<i32>>::new(pairs).flipped();
+        let mut y = vec![1, 2, 3, 4];
+        flipped.apply(&mut y, &vec![2]);
+        assert_eq!(y, vec![3]);
+    }
+}
@@ -0,0 +1,139 @@
+use super::{Cause, Class, Domain};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
+};
+
+/// Sees every domain change made during propagation.
+pub(super) trait Observer<C: Class, D: Domain> {
+    fn revised(&mut self, class: C, before: &D, after: &D, cause: &Cause<C>, support: &[(C, &D)]);
+
+    /// A global constraint over `classes` revised their domains, from
+    /// `before` to what is now in `after`. Each class is reported with the
+    /// others as its support.
+    fn revised_global(&mut self, classes: &[C], before: &[D], after: &HashMap<C, D>) {
+        let cause = Cause::Global(classes.to_vec());
+        for (idx, (c, old)) in classes.iter().zip(before).enumerate() {
+            let support = classes
+                .iter()
+                .zip(before)
+                .enumerate()
+                .filter(|(other, _)| *other != idx)
+                .map(|(_, (c, d))| (*c, d))
+                .collect::<Vec<_>>();
+            self.revised(*c, old, &after[c], &cause, &support);
+        }
+    }
+}
+
+impl<C: Class, D: Domain> Observer<C, D> for () {
+    fn revised(&mut self, _: C, _: &D, _: &D, _: &Cause<C>, _: &[(C, &D)]) {}
+    fn revised_global(&mut self, _: &[C], _: &[D], _: &HashMap<C, D>) {}
+}
+
+/// One value taken out of a domain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Removal<C, T> {
+    pub class: C,
+    pub value: T,
+    pub cause: Cause<C>,
+    /// The domains that were checked for support, as they were at the time
+    pub support: Vec<(C, Vec<T>)>,
+}
+
+impl<C: fmt::Debug, T: fmt::Debug> fmt::Display for Removal<C, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} != {:?} by {}", self.class, self.value, self.cause)?;
+        for (idx, (class, values)) in self.support.iter().enumerate() {
+            let sep = if idx == 0 { ", given" } else { "," };
+            write!(f, "{} {:?} in {:?}", sep, class, values)?;
+        }
+        Ok(())
+    }
+}
+
+/// Every removal made by [`AC3::solve_with_trace`](super::AC3::solve_with_trace), in order.
+#[derive(Debug, Clone)]
+pub struct SolveTrace<C, T> {
+    removals: Vec<Removal<C, T>>,
+}
+
+impl<C, T> Default for SolveTrace<C, T> {
+    fn default() -> Self {
+        SolveTrace {
+            removals: Vec::new(),
+        }
+    }
+}
+
+impl<C: Class, D: Domain> Observer<C, D> for SolveTrace<C, D::Value> {
+    fn revised(&mut self, class: C, before: &D, after: &D, cause: &Cause<C>, support: &[(C, &D)]) {
+        for value in before.values() {
+            if after.contains(&value) {
+                continue;
+            }
+            self.removals.push(Removal {
+                class,
+                value,
+                cause: cause.clone(),
+                support: support.iter().map(|(c, d)| (*c, d.values())).collect(),
+            });
+        }
+    }
+}
+
+impl<C: Class, T: PartialEq> SolveTrace<C, T> {
+    pub fn new() -> SolveTrace<C, T> {
+        SolveTrace::default()
+    }
+
+    pub fn removals(&self) -> &[Removal<C, T>] {
+        self.removals.as_slice()
+    }
+
+    fn position(&self, class: C, value: &T) -> Option<usize> {
+        self.removals
+            .iter()
+            .position(|r| r.class == class && r.value == *value)
+    }
+
+    /// The removals that led to `value` being taken from `class`, oldest
+    /// first and ending with that removal itself.
+    ///
+    /// A removal depends on every earlier removal from the domains it
+    /// looked to for support.
+    pub fn causes(&self, class: C, value: &T) -> Option<Vec<&Removal<C, T>>> {
+        let target = self.position(class, value)?;
+        let mut needed = BTreeSet::new();
+        let mut stack = vec![target];
+        while let Some(idx) = stack.pop() {
+            if !needed.insert(idx) {
+                continue;
+            }
+            for (support, _) in &self.removals[idx].support {
+                stack.extend(
+                    self.removals[..idx]
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, r)| r.class == *support)
+                        .map(|(i, _)| i),
+                );
+            }
+        }
+        Some(needed.into_iter().map(|i| &self.removals[i]).collect())
+    }
+
+    /// A numbered derivation of why `value` was taken from `class`
+    pub fn explain(&self, class: C, value: &T) -> Option<String>
+    where
+        T: fmt::Debug,
+    {
+        let steps = self.causes(class, value)?;
+        let lines = steps
+            .iter()
+            .enumerate()
+            .map(|(idx, r)| format!("{}. {}", idx + 1, r))
+            .collect::<Vec<_>>();
+        Some(lines.join("\n"))
+    }
+}
@@ -1,11 +1,11 @@
 pub mod alphabet {
     use std::fmt;
-    #[derive(Clone, Copy, PartialEq)]
+    #[derive(Clone, Copy, PartialEq, Eq, Hash)]
     pub struct Key(u32);
 
     impl From<char> for Key {
         fn from(c: char) -> Self {
-            let idx = (c.to_ascii_lowercase() as u8 - 'a' as u8) as usize;
+            let idx = (c.to_ascii_lowercase() as u8 - b'a') as usize;
             assert!(idx < 32);
             let a = u32::MAX >> idx;
             let b = u32::MAX >> (idx + 1);
@@ -15,7 +15,7 @@ pub mod alphabet {
 
     impl From<Key> for char {
         fn from(k: Key) -> Self {
-            ((k.0.leading_zeros() as u8) + ('a' as u8)) as char
+            ((k.0.leading_zeros() as u8) + b'a') as char
         }
     }
 
@@ -51,9 +51,12 @@ pub mod alphabet {
         pub fn len(&self) -> usize {
             self.0.count_ones() as usize
         }
+        pub fn is_empty(&self) -> bool {
+            self.0 == 0
+        }
         pub fn iter(self) -> impl Iterator<Item = Key> {
             ('a'..='z')
-                .map(|c| Key::from(c))
+                .map(Key::from)
                 .filter(move |k| self.contains(*k))
         }
     }
@@ -79,7 +82,7 @@ pub mod alphabet {
                 let rc = char::from(k);
                 assert_eq!(rc, c);
             }
-            for (cap_c, c) in ('A'..='Z').into_iter().zip('a'..='z') {
+            for (cap_c, c) in ('A'..='Z').zip('a'..='z') {
                 let k = Key::from(cap_c);
                 let rc = char::from(k);
                 assert_eq!(rc, c);
@@ -0,0 +1,189 @@
+use super::point3::Point3;
+
+/// An axis aligned box, inclusive of both corners.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cuboid {
+    pub min: Point3<i64>,
+    pub max: Point3<i64>,
+}
+
+impl Cuboid {
+    /// The corners may be given in any order
+    pub fn new(a: Point3<i64>, b: Point3<i64>) -> Cuboid {
+        Cuboid {
+            min: Point3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+            max: Point3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)),
+        }
+    }
+
+    pub fn volume(&self) -> i128 {
+        let side = |lo: i64, hi: i64| hi as i128 - lo as i128 + 1;
+        side(self.min.x, self.max.x) * side(self.min.y, self.max.y) * side(self.min.z, self.max.z)
+    }
+
+    pub fn contains(&self, p: Point3<i64>) -> bool {
+        (self.min.x..=self.max.x).contains(&p.x)
+            && (self.min.y..=self.max.y).contains(&p.y)
+            && (self.min.z..=self.max.z).contains(&p.z)
+    }
+
+    pub fn intersect(&self, other: &Cuboid) -> Option<Cuboid> {
+        let min = Point3::new(
+            self.min.x.max(other.min.x),
+            self.min.y.max(other.min.y),
+            self.min.z.max(other.min.z),
+        );
+        let max = Point3::new(
+            self.max.x.min(other.max.x),
+            self.max.y.min(other.max.y),
+            self.max.z.min(other.max.z),
+        );
+        if min.x > max.x || min.y > max.y || min.z > max.z {
+            None
+        } else {
+            Some(Cuboid { min, max })
+        }
+    }
+}
+
+/// A union of cuboids with cuboids switched on and off in sequence.
+///
+/// Uses signed overlap bookkeeping: every operation adds the negated
+/// intersection with each existing entry, so the signed volumes always sum
+/// to the lit volume.
+#[derive(Debug, Clone, Default)]
+pub struct CuboidSet {
+    entries: Vec<(Cuboid, i8)>,
+}
+
+impl CuboidSet {
+    pub fn new() -> CuboidSet {
+        CuboidSet::default()
+    }
+
+    fn cancel_overlaps(&mut self, c: &Cuboid) {
+        let overlaps = self
+            .entries
+            .iter()
+            .filter_map(|(e, sign)| e.intersect(c).map(|i| (i, -sign)))
+            .collect::<Vec<_>>();
+        self.entries.extend(overlaps);
+    }
+
+    pub fn on(&mut self, c: Cuboid) {
+        self.cancel_overlaps(&c);
+        self.entries.push((c, 1));
+    }
+
+    pub fn off(&mut self, c: Cuboid) {
+        self.cancel_overlaps(&c);
+    }
+
+    pub fn set(&mut self, c: Cuboid, lit: bool) {
+        if lit {
+            self.on(c)
+        } else {
+            self.off(c)
+        }
+    }
+
+    pub fn volume(&self) -> i128 {
+        self.entries
+            .iter()
+            .map(|(c, sign)| c.volume() * *sign as i128)
+            .sum()
+    }
+
+    pub fn contains(&self, p: Point3<i64>) -> bool {
+        let coverage: i64 = self
+            .entries
+            .iter()
+            .filter(|(c, _)| c.contains(p))
+            .map(|(_, sign)| *sign as i64)
+            .sum();
+        coverage > 0
+    }
+
+    /// Only the parts of the set inside `region`
+    pub fn clip(&self, region: &Cuboid) -> CuboidSet {
+        CuboidSet {
+            entries: self
+                .entries
+                .iter()
+                .filter_map(|(c, sign)| c.intersect(region).map(|i| (i, *sign)))
+                .collect(),
+        }
+    }
+
+    /// Drop entries that cancel each other out exactly
+    pub fn compact(&mut self) {
+        let mut counts: std::collections::HashMap<Cuboid, i64> = Default::default();
+        for (c, sign) in &self.entries {
+            *counts.entry(*c).or_default() += *sign as i64;
+        }
+        let mut entries = Vec::with_capacity(counts.len());
+        for (c, n) in counts {
+            let sign = n.signum() as i8;
+            entries.extend((0..n.unsigned_abs()).map(|_| (c, sign)));
+        }
+        self.entries = entries;
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cube(a: (i64, i64, i64), b: (i64, i64, i64)) -> Cuboid {
+        Cuboid::new(a.into(), b.into())
+    }
+
+    #[test]
+    fn reboot_small() {
+        let mut s = CuboidSet::new();
+        s.on(cube((10, 10, 10), (12, 12, 12)));
+        assert_eq!(s.volume(), 27);
+        s.on(cube((11, 11, 11), (13, 13, 13)));
+        assert_eq!(s.volume(), 27 + 19);
+        s.off(cube((9, 9, 9), (11, 11, 11)));
+        assert_eq!(s.volume(), 27 + 19 - 8);
+        s.on(cube((10, 10, 10), (10, 10, 10)));
+        assert_eq!(s.volume(), 39);
+
+        assert!(s.contains(Point3::new(10, 10, 10)));
+        assert!(!s.contains(Point3::new(11, 11, 11)));
+        assert!(s.contains(Point3::new(13, 13, 13)));
+        assert!(!s.contains(Point3::new(14, 13, 13)));
+    }
+
+    #[test]
+    fn clip_and_compact() {
+        let mut s = CuboidSet::new();
+        s.on(cube((-100, -100, -100), (100, 100, 100)));
+        s.off(cube((0, 0, 0), (200, 200, 200)));
+        let region = cube((-50, -50, -50), (50, 50, 50));
+        let clipped = s.clip(&region);
+        assert_eq!(clipped.volume(), 101i128.pow(3) - 51i128.pow(3));
+
+        s.on(cube((0, 0, 0), (200, 200, 200)));
+        s.off(cube((-100, -100, -100), (200, 200, 200)));
+        assert_eq!(s.volume(), 0);
+        s.compact();
+        assert!(s.is_empty());
+    }
+
+    #[test]
+    fn volume_beyond_i64() {
+        let big = 3_000_000_000;
+        let mut s = CuboidSet::new();
+        s.on(cube((0, 0, 0), (big - 1, big - 1, big - 1)));
+        assert_eq!(s.volume(), (big as i128).pow(3));
+    }
+}
@@ -0,0 +1,84 @@
+use std::{
+    fmt,
+    ops::{Add, Neg, Sub},
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Point3<T = i64> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
+}
+
+impl<T> Point3<T> {
+    pub const fn new(x: T, y: T, z: T) -> Point3<T> {
+        Point3 { x, y, z }
+    }
+}
+
+impl<T: Add> Add for Point3<T> {
+    type Output = Point3<<T as Add>::Output>;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Point3 {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+            z: self.z + rhs.z,
+        }
+    }
+}
+
+impl<T: Sub> Sub for Point3<T> {
+    type Output = Point3<<T as Sub>::Output>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Point3 {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+            z: self.z - rhs.z,
+        }
+    }
+}
+
+impl<T: Neg> Neg for Point3<T> {
+    type Output = Point3<<T as Neg>::Output>;
+
+    fn neg(self) -> Self::Output {
+        Point3 {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+}
+
+impl<T> From<(T, T, T)> for Point3<T> {
+    fn from(t: (T, T, T)) -> Self {
+        Point3::new(t.0, t.1, t.2)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Point3<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({:?}, {:?}, {:?})", self.x, self.y, self.z)
+    }
+}
+impl<T: fmt::Display> fmt::Display for Point3<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {}, {})", self.x, self.y, self.z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arithmetic() {
+        let a = Point3::new(1, 2, 3);
+        let b = Point3::new(-4, 0, 7);
+        assert_eq!(a + b, Point3::new(-3, 2, 10));
+        assert_eq!(a - b, Point3::new(5, 2, -4));
+        assert_eq!(-a, Point3::new(-1, -2, -3));
+    }
+}
@@ -0,0 +1,179 @@
+use crate::grid::compass::Direction;
+use crate::Point;
+
+/// A simple polygon on the integer lattice.
+///
+/// All the measurements are done in `i128`, so vertices anywhere in the
+/// `i64` range are fine.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polygon {
+    vertices: Vec<Point<i64>>,
+}
+
+impl Polygon {
+    pub fn new(vertices: Vec<Point<i64>>) -> Polygon {
+        Polygon { vertices }
+    }
+
+    /// Trace a closed path starting at the origin, e.g. a dig plan.
+    pub fn from_steps<I>(steps: I) -> Polygon
+    where
+        I: IntoIterator<Item = (Direction, i64)>,
+    {
+        let mut current = Point::new(0, 0);
+        let mut vertices = vec![current];
+        for (d, len) in steps {
+            current += d.delta().scale(len);
+            vertices.push(current);
+        }
+        if vertices.len() > 1 && vertices.first() == vertices.last() {
+            vertices.pop();
+        }
+        Polygon { vertices }
+    }
+
+    pub fn vertices(&self) -> &[Point<i64>] {
+        self.vertices.as_slice()
+    }
+
+    fn edges(&self) -> impl Iterator<Item = (Point<i64>, Point<i64>)> + '_ {
+        self.vertices
+            .iter()
+            .zip(self.vertices.iter().cycle().skip(1))
+            .map(|(a, b)| (*a, *b))
+    }
+
+    /// Shoelace formula, positive for anticlockwise vertices
+    pub fn signed_double_area(&self) -> i128 {
+        self.edges()
+            .map(|(a, b)| a.x as i128 * b.y as i128 - b.x as i128 * a.y as i128)
+            .sum()
+    }
+
+    /// Twice the enclosed area, which is always an integer for lattice polygons
+    pub fn double_area(&self) -> i128 {
+        self.signed_double_area().abs()
+    }
+
+    /// The enclosed area, rounded down when it falls on a half unit
+    pub fn area(&self) -> i128 {
+        self.double_area() / 2
+    }
+
+    /// Lattice points on the boundary, which is the perimeter for axis aligned edges
+    pub fn boundary_len(&self) -> i128 {
+        self.edges()
+            .map(|(a, b)| {
+                let dx = b.x as i128 - a.x as i128;
+                let dy = b.y as i128 - a.y as i128;
+                gcd(dx.abs(), dy.abs())
+            })
+            .sum()
+    }
+
+    /// Lattice points strictly inside, by Pick's theorem: A = i + b/2 - 1
+    pub fn interior_points(&self) -> i128 {
+        (self.double_area() - self.boundary_len() + 2) / 2
+    }
+
+    /// Cells dug out when every lattice point on or inside the boundary is a cell
+    pub fn enclosed_cells(&self) -> i128 {
+        self.interior_points() + self.boundary_len()
+    }
+}
+
+fn gcd(mut a: i128, mut b: i128) -> i128 {
+    while b != 0 {
+        let t = a % b;
+        a = b;
+        b = t;
+    }
+    a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIG_PLAN: &str = "R 6 (#70c710)
+D 5 (#0dc571)
+L 2 (#5713f0)
+D 2 (#d2c081)
+R 2 (#59c680)
+D 2 (#411b91)
+L 5 (#8ceee2)
+U 2 (#caa173)
+L 1 (#1b58a2)
+U 2 (#caa171)
+R 2 (#7807d2)
+U 3 (#a77fa3)
+L 2 (#015232)
+U 2 (#7a21e3)";
+
+    fn direction(c: char) -> Direction {
+        match c {
+            'U' | '3' => Direction::North,
+            'R' | '0' => Direction::East,
+            'D' | '1' => Direction::South,
+            'L' | '2' => Direction::West,
+            _ => panic!("bad direction {:?}", c),
+        }
+    }
+
+    #[test]
+    fn dig_plan_small() {
+        let steps = DIG_PLAN.lines().map(|l| {
+            let mut words = l.split_whitespace();
+            let d = direction(words.next().unwrap().chars().next().unwrap());
+            (d, words.next().unwrap().parse::<i64>().unwrap())
+        });
+        let poly = Polygon::from_steps(steps);
+        assert_eq!(poly.vertices().len(), 14);
+        assert_eq!(poly.boundary_len(), 38);
+        assert_eq!(poly.enclosed_cells(), 62);
+    }
+
+    #[test]
+    fn dig_plan_hex() {
+        let steps = DIG_PLAN.lines().map(|l| {
+            let hex = &l[l.find('#').unwrap() + 1..l.len() - 1];
+            let len = i64::from_str_radix(&hex[..5], 16).unwrap();
+            (direction(hex.chars().last().unwrap()), len)
+        });
+        let poly = Polygon::from_steps(steps);
+        assert_eq!(poly.enclosed_cells(), 952408144115);
+    }
+
+    #[test]
+    fn pick_on_triangle() {
+        let poly = Polygon::new(vec![(0, 0).into(), (4, 0).into(), (0, 3).into()]);
+        assert_eq!(poly.double_area(), 12);
+        assert_eq!(poly.area(), 6);
+        // 4 + 3 + gcd(4, 3)
+        assert_eq!(poly.boundary_len(), 8);
+        assert_eq!(poly.interior_points(), 3);
+    }
+
+    #[test]
+    fn huge_coordinates() {
+        let big = 3_000_000_000i64;
+        let poly = Polygon::from_steps(vec![
+            (Direction::East, big),
+            (Direction::North, big),
+            (Direction::West, big),
+            (Direction::South, big),
+        ]);
+        assert_eq!(poly.area(), big as i128 * big as i128);
+        assert_eq!(poly.enclosed_cells(), (big as i128 + 1).pow(2));
+        assert!(poly.signed_double_area() > 0);
+
+        // edges spanning most of the i64 range would overflow an i64 difference
+        let poly = Polygon::new(vec![
+            (i64::MIN, i64::MIN).into(),
+            (i64::MAX, i64::MIN).into(),
+            (i64::MAX, i64::MAX).into(),
+            (i64::MIN, i64::MAX).into(),
+        ]);
+        assert_eq!(poly.boundary_len(), 4 * (u64::MAX as i128));
+    }
+}
@@ -0,0 +1,192 @@
+use super::point3::Point3;
+use std::collections::HashMap;
+
+const PERMUTATIONS: [[u8; 3]; 6] = [
+    [0, 1, 2],
+    [0, 2, 1],
+    [1, 0, 2],
+    [1, 2, 0],
+    [2, 0, 1],
+    [2, 1, 0],
+];
+
+/// An axis aligned orientation: each output axis is a signed input axis.
+///
+/// The 24 proper rotations form a group; including reflections gives all 48.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rotation3 {
+    axes: [u8; 3],
+    signs: [i8; 3],
+}
+
+fn coord(p: Point3<i64>, axis: u8) -> i64 {
+    match axis {
+        0 => p.x,
+        1 => p.y,
+        _ => p.z,
+    }
+}
+
+impl Rotation3 {
+    pub const IDENTITY: Rotation3 = Rotation3 {
+        axes: [0, 1, 2],
+        signs: [1, 1, 1],
+    };
+
+    /// All 48 orientations, reflections included
+    pub fn all() -> impl Iterator<Item = Rotation3> {
+        PERMUTATIONS.iter().flat_map(|axes| {
+            (0..8).map(move |bits| Rotation3 {
+                axes: *axes,
+                signs: [
+                    if bits & 1 == 0 { 1 } else { -1 },
+                    if bits & 2 == 0 { 1 } else { -1 },
+                    if bits & 4 == 0 { 1 } else { -1 },
+                ],
+            })
+        })
+    }
+
+    /// The 24 rotations that do not mirror
+    pub fn proper() -> impl Iterator<Item = Rotation3> {
+        Rotation3::all().filter(|r| r.is_proper())
+    }
+
+    fn determinant(&self) -> i8 {
+        let [a, b, c] = self.axes;
+        let inversions = (a > b) as u8 + (a > c) as u8 + (b > c) as u8;
+        let parity = if inversions & 1 == 0 { 1 } else { -1 };
+        parity * self.signs.iter().product::<i8>()
+    }
+
+    pub fn is_proper(&self) -> bool {
+        self.determinant() == 1
+    }
+
+    pub fn apply(&self, p: Point3<i64>) -> Point3<i64> {
+        let c = |i: usize| self.signs[i] as i64 * coord(p, self.axes[i]);
+        Point3::new(c(0), c(1), c(2))
+    }
+
+    /// `self.compose(other).apply(p) == self.apply(other.apply(p))`
+    pub fn compose(&self, other: &Rotation3) -> Rotation3 {
+        let mut r = Rotation3::IDENTITY;
+        for i in 0..3 {
+            let via = self.axes[i] as usize;
+            r.axes[i] = other.axes[via];
+            r.signs[i] = self.signs[i] * other.signs[via];
+        }
+        r
+    }
+
+    pub fn inverse(&self) -> Rotation3 {
+        let mut r = Rotation3::IDENTITY;
+        for i in 0..3 {
+            let a = self.axes[i] as usize;
+            r.axes[a] = i as u8;
+            r.signs[a] = self.signs[i];
+        }
+        r
+    }
+}
+
+/// Maps points from one scanner's frame into another's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Alignment {
+    pub rotation: Rotation3,
+    pub translation: Point3<i64>,
+}
+
+impl Alignment {
+    pub fn apply(&self, p: Point3<i64>) -> Point3<i64> {
+        self.rotation.apply(p) + self.translation
+    }
+}
+
+/// Find a proper rotation and translation that lands at least `min_shared`
+/// points of `other` on points of `reference`.
+pub fn align(
+    reference: &[Point3<i64>],
+    other: &[Point3<i64>],
+    min_shared: usize,
+) -> Option<Alignment> {
+    let mut votes = HashMap::new();
+    for rotation in Rotation3::proper() {
+        votes.clear();
+        let rotated = other.iter().map(|p| rotation.apply(*p)).collect::<Vec<_>>();
+        for r in reference {
+            for o in &rotated {
+                let count = votes.entry(*r - *o).or_insert(0usize);
+                *count += 1;
+                if *count >= min_shared {
+                    return Some(Alignment {
+                        rotation,
+                        translation: *r - *o,
+                    });
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn group_sizes() {
+        assert_eq!(Rotation3::all().collect::<HashSet<_>>().len(), 48);
+        let proper = Rotation3::proper().collect::<Vec<_>>();
+        assert_eq!(proper.len(), 24);
+        let p = Point3::new(1, 2, 3);
+        let images = proper.iter().map(|r| r.apply(p)).collect::<HashSet<_>>();
+        assert_eq!(images.len(), 24);
+    }
+
+    #[test]
+    fn compose_and_inverse() {
+        let p = Point3::new(5, -7, 11);
+        for a in Rotation3::all() {
+            assert_eq!(a.compose(&a.inverse()), Rotation3::IDENTITY);
+            assert_eq!(a.inverse().apply(a.apply(p)), p);
+            for b in Rotation3::all() {
+                let ab = a.compose(&b);
+                assert_eq!(ab.apply(p), a.apply(b.apply(p)));
+                assert_eq!(ab.is_proper(), a.is_proper() == b.is_proper());
+            }
+        }
+    }
+
+    #[test]
+    fn align_point_clouds() {
+        let mut state = 7u64;
+        let mut rand = || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((state >> 33) % 2001) as i64 - 1000
+        };
+        let reference = (0..25)
+            .map(|_| Point3::new(rand(), rand(), rand()))
+            .collect::<Vec<_>>();
+
+        let hidden = Rotation3::proper().nth(17).unwrap();
+        let offset = Point3::new(68, -1246, -43);
+        // the other scanner sees 12 of the same beacons plus some of its own
+        let mut other = reference[..12]
+            .iter()
+            .map(|p| hidden.inverse().apply(*p - offset))
+            .collect::<Vec<_>>();
+        other.extend((0..10).map(|_| Point3::new(rand(), rand(), rand())));
+
+        let found = align(&reference, &other, 12).unwrap();
+        assert_eq!(found.rotation, hidden);
+        assert_eq!(found.translation, offset);
+        for p in &other[..12] {
+            assert!(reference.contains(&found.apply(*p)));
+        }
+        assert!(align(&reference, &other[12..], 12).is_none());
+    }
+}
@@ -0,0 +1,268 @@
+use super::{fixed_grid::FixedGrid, pointmap_grid::PointMap};
+use crate::grid::point::Point;
+use std::{
+    collections::HashSet,
+    fmt,
+    io::{self, IsTerminal, Write},
+};
+
+const RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AnsiColor {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    BrightBlack,
+    BrightRed,
+    BrightGreen,
+    BrightYellow,
+    BrightBlue,
+    BrightMagenta,
+    BrightCyan,
+    BrightWhite,
+    /// 256 color palette index
+    Fixed(u8),
+    /// 24 bit color
+    Rgb(u8, u8, u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layer {
+    Foreground,
+    Background,
+}
+
+impl AnsiColor {
+    fn basic_index(self) -> Option<u8> {
+        let idx = match self {
+            AnsiColor::Black => 0,
+            AnsiColor::Red => 1,
+            AnsiColor::Green => 2,
+            AnsiColor::Yellow => 3,
+            AnsiColor::Blue => 4,
+            AnsiColor::Magenta => 5,
+            AnsiColor::Cyan => 6,
+            AnsiColor::White => 7,
+            AnsiColor::BrightBlack => 60,
+            AnsiColor::BrightRed => 61,
+            AnsiColor::BrightGreen => 62,
+            AnsiColor::BrightYellow => 63,
+            AnsiColor::BrightBlue => 64,
+            AnsiColor::BrightMagenta => 65,
+            AnsiColor::BrightCyan => 66,
+            AnsiColor::BrightWhite => 67,
+            AnsiColor::Fixed(_) | AnsiColor::Rgb(..) => return None,
+        };
+        Some(idx)
+    }
+
+    fn write_escape<W: fmt::Write>(self, w: &mut W, layer: Layer) -> fmt::Result {
+        let base = match layer {
+            Layer::Foreground => 30,
+            Layer::Background => 40,
+        };
+        if let Some(idx) = self.basic_index() {
+            return write!(w, "\x1b[{}m", base + idx as u32);
+        }
+        match self {
+            AnsiColor::Fixed(n) => write!(w, "\x1b[{};5;{}m", base + 8, n),
+            AnsiColor::Rgb(r, g, b) => write!(w, "\x1b[{};2;{};{};{}m", base + 8, r, g, b),
+            _ => unreachable!("basic colors are handled above"),
+        }
+    }
+}
+
+/// Renders grids with per-cell colors and highlighted points on top.
+///
+/// Highlights are drawn as a background color so the cell character stays
+/// readable. Unless forced with [`AnsiRenderer::colored`], color is only
+/// emitted when stdout is a terminal; otherwise the output matches `Display`.
+pub struct AnsiRenderer<F> {
+    color: F,
+    highlight: HashSet<Point<i64>>,
+    highlight_color: AnsiColor,
+    colored: Option<bool>,
+}
+
+impl<F> AnsiRenderer<F> {
+    pub fn new(color: F) -> AnsiRenderer<F> {
+        AnsiRenderer {
+            color,
+            highlight: HashSet::new(),
+            highlight_color: AnsiColor::BrightYellow,
+            colored: None,
+        }
+    }
+
+    pub fn highlight_path(mut self, path: &[Point<i64>]) -> AnsiRenderer<F> {
+        self.highlight.extend(path.iter().copied());
+        self
+    }
+    pub fn highlight_points<I>(mut self, points: I) -> AnsiRenderer<F>
+    where
+        I: IntoIterator<Item = Point<i64>>,
+    {
+        self.highlight.extend(points);
+        self
+    }
+    pub fn highlight_color(mut self, color: AnsiColor) -> AnsiRenderer<F> {
+        self.highlight_color = color;
+        self
+    }
+    /// Force color on or off instead of detecting a terminal
+    pub fn colored(mut self, colored: bool) -> AnsiRenderer<F> {
+        self.colored = Some(colored);
+        self
+    }
+
+    fn use_color(&self) -> bool {
+        self.colored.unwrap_or_else(|| io::stdout().is_terminal())
+    }
+
+    fn write_cell<T, W>(&self, w: &mut W, p: Point<i64>, cell: Option<&T>) -> fmt::Result
+    where
+        T: fmt::Display,
+        F: Fn(&T) -> Option<AnsiColor>,
+        W: fmt::Write,
+    {
+        let fg = cell.and_then(|c| (self.color)(c));
+        let highlighted = self.highlight.contains(&p);
+        if let Some(fg) = fg {
+            fg.write_escape(w, Layer::Foreground)?;
+        }
+        if highlighted {
+            self.highlight_color.write_escape(w, Layer::Background)?;
+        }
+        match cell {
+            Some(c) => write!(w, "{}", c)?,
+            None => write!(w, " ")?,
+        }
+        if fg.is_some() || highlighted {
+            write!(w, "{}", RESET)?;
+        }
+        Ok(())
+    }
+
+    pub fn render_grid<T>(&self, grid: &FixedGrid<T>) -> String
+    where
+        T: fmt::Display,
+        F: Fn(&T) -> Option<AnsiColor>,
+    {
+        if !self.use_color() {
+            return grid.to_string();
+        }
+        let mut out = String::new();
+        for (idx, (p, cell)) in grid.points().zip(grid.raw_iter()).enumerate() {
+            if idx != 0 && p.x == 0 {
+                out.push('\n');
+            }
+            self.write_cell(&mut out, p, Some(cell))
+                .expect("writing to a String does not fail");
+        }
+        out
+    }
+
+    pub fn render_points<T>(&self, grid: &PointMap<T>) -> String
+    where
+        T: fmt::Display,
+        F: Fn(&T) -> Option<AnsiColor>,
+    {
+        if !self.use_color() {
+            return grid.to_string();
+        }
+        let bounds = match grid.bounds() {
+            Some(b) => b,
+            None => return String::new(),
+        };
+        let mut out = String::new();
+        for idy in bounds.min_y..(bounds.max_y + 1) {
+            for idx in bounds.min_x..(bounds.max_x + 1) {
+                let p = Point::new(idx, idy);
+                self.write_cell(&mut out, p, grid.get(&p))
+                    .expect("writing to a String does not fail");
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    pub fn print_grid<T>(&self, grid: &FixedGrid<T>) -> io::Result<()>
+    where
+        T: fmt::Display,
+        F: Fn(&T) -> Option<AnsiColor>,
+    {
+        let mut stdout = io::stdout().lock();
+        writeln!(stdout, "{}", self.render_grid(grid))
+    }
+
+    pub fn print_points<T>(&self, grid: &PointMap<T>) -> io::Result<()>
+    where
+        T: fmt::Display,
+        F: Fn(&T) -> Option<AnsiColor>,
+    {
+        let mut stdout = io::stdout().lock();
+        write!(stdout, "{}", self.render_points(grid))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn maze() -> FixedGrid<char> {
+        FixedGrid::parse_ascii_grid("#.#\n#..", Ok).unwrap()
+    }
+
+    fn walls(c: &char) -> Option<AnsiColor> {
+        if *c == '#' {
+            Some(AnsiColor::Blue)
+        } else {
+            None
+        }
+    }
+
+    #[test]
+    fn plain_matches_display() {
+        let g = maze();
+        let r = AnsiRenderer::new(walls)
+            .highlight_path(&[Point::new(1, 0)])
+            .colored(false);
+        assert_eq!(r.render_grid(&g), g.to_string());
+    }
+
+    #[test]
+    fn colors_and_highlight() {
+        let g = maze();
+        let r = AnsiRenderer::new(walls)
+            .highlight_path(&[Point::new(1, 0), Point::new(1, 1), Point::new(2, 1)])
+            .highlight_color(AnsiColor::Rgb(1, 2, 3))
+            .colored(true);
+        let blue = "\x1b[34m#\x1b[0m";
+        let path = "\x1b[48;2;1;2;3m.\x1b[0m";
+        assert_eq!(
+            r.render_grid(&g),
+            format!("{b}{p}{b}\n{b}{p}{p}", b = blue, p = path)
+        );
+    }
+
+    #[test]
+    fn pointmap_highlight_gap() {
+        let mut pm = PointMap::default();
+        pm.insert(Point::new(0, 0), 'x');
+        pm.insert(Point::new(2, 0), 'y');
+        let r = AnsiRenderer::new(|_: &char| Some(AnsiColor::Fixed(200)))
+            .highlight_points(vec![Point::new(1, 0)])
+            .highlight_color(AnsiColor::BrightRed)
+            .colored(true);
+        assert_eq!(
+            r.render_points(&pm),
+            "\x1b[38;5;200mx\x1b[0m\x1b[101m \x1b[0m\x1b[38;5;200my\x1b[0m\n"
+        );
+    }
+}
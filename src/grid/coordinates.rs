@@ -1,9 +1,16 @@
-use std::{hash::Hash, ops::Rem};
+use std::{
+    hash::Hash,
+    ops::{Add, Neg, Rem, Sub},
+};
 
 pub trait Zero {
     fn zero() -> Self;
 }
 
+pub trait One {
+    fn one() -> Self;
+}
+
 pub trait FromUsize {
     fn from_usize(u: usize) -> Self;
     fn to_usize(self) -> usize;
@@ -13,6 +20,32 @@ pub trait Coordinate: Ord + Hash + Copy + Zero + FromUsize + Rem<Output = Self>
 
 impl<T> Coordinate for T where T: Ord + Hash + Copy + Zero + FromUsize + Rem<Output = T> {}
 
+pub trait SignedCoordinate:
+    Coordinate + One + Add<Output = Self> + Sub<Output = Self> + Neg<Output = Self>
+{
+    fn signum(self) -> Self {
+        if self > Self::zero() {
+            Self::one()
+        } else if self < Self::zero() {
+            -Self::one()
+        } else {
+            Self::zero()
+        }
+    }
+    fn abs(self) -> Self {
+        if self < Self::zero() {
+            -self
+        } else {
+            self
+        }
+    }
+}
+
+impl<T> SignedCoordinate for T where
+    T: Coordinate + One + Add<Output = T> + Sub<Output = T> + Neg<Output = T>
+{
+}
+
 impl Zero for i64 {
     fn zero() -> Self {
         0
@@ -38,6 +71,16 @@ impl Zero for usize {
         0
     }
 }
+impl One for i64 {
+    fn one() -> Self {
+        1
+    }
+}
+impl One for i32 {
+    fn one() -> Self {
+        1
+    }
+}
 impl FromUsize for i32 {
     fn from_usize(u: usize) -> Self {
         u as i32
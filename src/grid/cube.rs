@@ -0,0 +1,342 @@
+use super::{
+    compass::Direction,
+    fixed_grid::FixedGrid,
+    walker::{EdgePolicy, Tile},
+};
+use crate::{geometry::point3::Point3, grid::point::Point};
+
+fn scale(p: Point3<i64>, k: i64) -> Point3<i64> {
+    Point3::new(p.x * k, p.y * k, p.z * k)
+}
+
+fn opposite(d: Direction) -> Direction {
+    d.clockwise().clockwise()
+}
+
+/// Swap north and south, between grid directions and the `+y` north used for folding
+fn mirror(d: Direction) -> Direction {
+    match d {
+        Direction::North => Direction::South,
+        Direction::South => Direction::North,
+        d => d,
+    }
+}
+
+/// One face of the net, and where it ended up after folding.
+///
+/// Positions are on a unit cube: a face-local point `(a, b)` with `a` and `b`
+/// in `0..=1` sits at `origin + a * u + b * v`. `normal` points into the cube.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Face {
+    block: Point<i64>,
+    origin: Point3<i64>,
+    u: Point3<i64>,
+    v: Point3<i64>,
+    normal: Point3<i64>,
+}
+
+impl Face {
+    fn corner(&self, a: i64, b: i64) -> Point3<i64> {
+        self.origin + scale(self.u, a) + scale(self.v, b)
+    }
+
+    /// The corners at either end of the edge you leave through going `dir`
+    fn edge(&self, dir: Direction) -> (Point3<i64>, Point3<i64>) {
+        match dir {
+            Direction::East => (self.corner(1, 0), self.corner(1, 1)),
+            Direction::West => (self.corner(0, 0), self.corner(0, 1)),
+            Direction::North => (self.corner(0, 1), self.corner(1, 1)),
+            Direction::South => (self.corner(0, 0), self.corner(1, 0)),
+        }
+    }
+
+    /// The face that shares the edge on the `dir` side, bent in towards the normal
+    fn fold(&self, dir: Direction) -> Face {
+        let block = self.block + dir.delta();
+        let Face {
+            origin,
+            u,
+            v,
+            normal,
+            ..
+        } = *self;
+        match dir {
+            Direction::East => Face {
+                block,
+                origin: origin + u,
+                u: normal,
+                v,
+                normal: -u,
+            },
+            Direction::West => Face {
+                block,
+                origin: origin + normal,
+                u: -normal,
+                v,
+                normal: u,
+            },
+            Direction::North => Face {
+                block,
+                origin: origin + v,
+                u,
+                v: normal,
+                normal: -v,
+            },
+            Direction::South => Face {
+                block,
+                origin: origin + normal,
+                u,
+                v: -normal,
+                normal: v,
+            },
+        }
+    }
+}
+
+/// A 2D net of six square faces, folded up into a cube.
+///
+/// Any of the eleven cube nets works, at any face size. Walking off the edge
+/// of a face continues on whichever face shares that edge once folded, with
+/// the facing rotated to match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CubeNet {
+    size: i64,
+    faces: Vec<Face>,
+}
+
+impl CubeNet {
+    /// Find the faces among the non-void cells of `grid` and fold them up
+    pub fn new<T>(grid: &FixedGrid<T>, tile: &dyn Fn(&T) -> Tile) -> anyhow::Result<CubeNet> {
+        let cells = grid
+            .points()
+            .filter(|p| tile(&grid[*p]) != Tile::Void)
+            .collect::<Vec<_>>();
+        let size = (1i64..).find(|s| 6 * s * s >= cells.len() as i64).unwrap();
+        if 6 * size * size != cells.len() as i64 {
+            anyhow::bail!(
+                "{} cells can not be split into six square faces",
+                cells.len()
+            );
+        }
+
+        let mut blocks: Vec<(Point<i64>, i64)> = Vec::new();
+        for p in &cells {
+            let block = Point::new(p.x.div_euclid(size), p.y.div_euclid(size));
+            match blocks.iter_mut().find(|(b, _)| *b == block) {
+                Some((_, count)) => *count += 1,
+                None => blocks.push((block, 1)),
+            }
+        }
+        if blocks.len() != 6 || blocks.iter().any(|(_, count)| *count != size * size) {
+            anyhow::bail!("the map is not made of six {}x{} faces", size, size);
+        }
+
+        let mut faces = vec![Face {
+            block: blocks[0].0,
+            origin: Point3::new(0, 0, 0),
+            u: Point3::new(1, 0, 0),
+            v: Point3::new(0, 1, 0),
+            normal: Point3::new(0, 0, 1),
+        }];
+        let mut idx = 0;
+        while idx < faces.len() {
+            let face = faces[idx];
+            for dir in Direction::iter() {
+                let block = face.block + dir.delta();
+                let in_net = blocks.iter().any(|(b, _)| *b == block);
+                if in_net && faces.iter().all(|f| f.block != block) {
+                    faces.push(face.fold(dir));
+                }
+            }
+            idx += 1;
+        }
+        if faces.len() != 6 {
+            anyhow::bail!("the faces are not all connected");
+        }
+        let centre = |f: &Face| scale(f.origin, 2) + f.u + f.v;
+        for (i, a) in faces.iter().enumerate() {
+            if faces[..i].iter().any(|b| centre(b) == centre(a)) {
+                anyhow::bail!("faces overlap when folded, this is not a cube net");
+            }
+        }
+
+        Ok(CubeNet { size, faces })
+    }
+
+    /// The side length of each face
+    pub fn size(&self) -> i64 {
+        self.size
+    }
+
+    /// The top left cell of each face in the grid
+    pub fn faces(&self) -> impl Iterator<Item = Point<i64>> + '_ {
+        self.faces.iter().map(move |f| f.block.scale(self.size))
+    }
+
+    fn face_at(&self, pos: Point<i64>) -> Option<&Face> {
+        let block = Point::new(pos.x.div_euclid(self.size), pos.y.div_euclid(self.size));
+        self.faces.iter().find(|f| f.block == block)
+    }
+
+    /// Index of the face `pos` is on, in the order of [`CubeNet::faces`]
+    pub fn face_of(&self, pos: Point<i64>) -> Option<usize> {
+        let face = self.face_at(pos)?;
+        self.faces.iter().position(|f| f == face)
+    }
+
+    /// Take one step from `pos`, going over an edge onto the adjoining face if needed.
+    ///
+    /// Directions are as on the grid, with north up the rows, see
+    /// [`grid_delta`](super::walker::grid_delta).
+    ///
+    /// # Panics
+    ///
+    /// If `pos` is not on one of the faces.
+    pub fn step(&self, pos: Point<i64>, dir: Direction) -> (Point<i64>, Direction) {
+        let (next, dir) = self.fold_step(pos, mirror(dir));
+        (next, mirror(dir))
+    }
+
+    fn fold_step(&self, pos: Point<i64>, dir: Direction) -> (Point<i64>, Direction) {
+        let next = pos + dir.delta();
+        if self.face_at(next).is_some() {
+            return (next, dir);
+        }
+
+        let face = self.face_at(pos).expect("position is not on the cube net");
+        let local = pos - face.block.scale(self.size);
+        let along = match dir {
+            Direction::East | Direction::West => local.y,
+            Direction::North | Direction::South => local.x,
+        };
+        let (start, end) = face.edge(dir);
+
+        for other in self.faces.iter().filter(|f| f.block != face.block) {
+            for exit in Direction::iter() {
+                let (a, b) = other.edge(exit);
+                let along = if (a, b) == (start, end) {
+                    along
+                } else if (a, b) == (end, start) {
+                    self.size - 1 - along
+                } else {
+                    continue;
+                };
+                let last = self.size - 1;
+                let local = match exit {
+                    Direction::East => Point::new(last, along),
+                    Direction::West => Point::new(0, along),
+                    Direction::North => Point::new(along, last),
+                    Direction::South => Point::new(along, 0),
+                };
+                return (other.block.scale(self.size) + local, opposite(exit));
+            }
+        }
+        unreachable!("every edge of a folded cube is shared by two faces")
+    }
+}
+
+impl<T> EdgePolicy<T> for CubeNet {
+    fn wrap(
+        &self,
+        _grid: &FixedGrid<T>,
+        _tile: &dyn Fn(&T) -> Tile,
+        pos: Point<i64>,
+        dir: Direction,
+    ) -> Option<(Point<i64>, Direction)> {
+        Some(self.step(pos, dir))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::walker::{monkey_map::*, Walker};
+
+    /// Blow up a layout of `#` blocks into a net with `size` sized faces
+    fn net(layout: &[&str], size: usize) -> FixedGrid<char> {
+        let width = layout.iter().map(|l| l.len()).max().unwrap();
+        let mut rows = Vec::new();
+        for line in layout {
+            let row = format!("{:width$}", line, width = width)
+                .chars()
+                .flat_map(|c| (0..size).map(move |_| if c == '#' { '.' } else { ' ' }))
+                .collect::<String>();
+            rows.extend((0..size).map(|_| row.clone()));
+        }
+        FixedGrid::parse_ascii_grid(&rows.join("\n"), Ok).unwrap()
+    }
+
+    fn check_walks(cube: &CubeNet) {
+        for corner in cube.faces() {
+            for dx in 0..cube.size() {
+                for dy in 0..cube.size() {
+                    let start = corner + Point::new(dx, dy);
+                    for dir in Direction::iter() {
+                        // going all the way around brings you back
+                        let mut at = (start, dir);
+                        for _ in 0..4 * cube.size() {
+                            at = cube.step(at.0, at.1);
+                        }
+                        assert_eq!(at, (start, dir));
+
+                        // and every step can be undone
+                        let (p, d) = cube.step(start, dir);
+                        let (back, d) = cube.step(p, opposite(d));
+                        assert_eq!((back, opposite(d)), (start, dir));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn detects_faces() {
+        let g = parse_monkey_map();
+        let cube = CubeNet::new(&g, &|c: &char| monkey_tile(c)).unwrap();
+        assert_eq!(cube.size(), 4);
+        assert_eq!(cube.faces().count(), 6);
+        assert_eq!(cube.face_of(Point::new(8, 0)), Some(0));
+        assert_eq!(cube.face_of(Point::new(0, 0)), None);
+        check_walks(&cube);
+    }
+
+    #[test]
+    fn any_net_folds() {
+        let nets: &[&[&str]] = &[
+            &[" # ", "###", " # ", " # "],
+            &["#   ", "####", "   #"],
+            &["##  ", " ###", " #  "],
+            &["##   ", " ##  ", "  ## "],
+            &["###   ", "  ### "],
+        ];
+        for (i, layout) in nets.iter().enumerate() {
+            let g = net(layout, i + 1);
+            let cube = CubeNet::new(&g, &|c: &char| monkey_tile(c))
+                .unwrap_or_else(|e| panic!("net {}: {}", i, e));
+            assert_eq!(cube.size(), i as i64 + 1);
+            check_walks(&cube);
+        }
+    }
+
+    #[test]
+    fn rejects_non_nets() {
+        let tile = |c: &char| monkey_tile(c);
+        assert!(CubeNet::new(&net(&["###", "###"], 2), &tile).is_err());
+        assert!(CubeNet::new(&net(&["####", "  # ", "   #"], 2), &tile).is_err());
+        assert!(CubeNet::new(&net(&["#####"], 2), &tile).is_err());
+        assert!(CubeNet::new(&net(&["#  ", "###", "  #", "  #"], 2), &tile).is_err());
+        let g = FixedGrid::parse_ascii_grid(".......", Ok).unwrap();
+        assert!(CubeNet::new(&g, &tile).is_err());
+    }
+
+    #[test]
+    fn monkey_map_cube() {
+        let g = parse_monkey_map();
+        let tile = |c: &char| monkey_tile(c);
+        let cube = CubeNet::new(&g, &tile).unwrap();
+        let start = g.points().find(|p| tile(&g[*p]) == Tile::Open).unwrap();
+        let mut w = Walker::new(start, Direction::East);
+        w.execute_on(&g, &tile, &cube, "10R5L5R10L4R5L5").unwrap();
+        assert_eq!(password(&w), 5031);
+    }
+}
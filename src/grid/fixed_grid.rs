@@ -16,7 +16,7 @@ impl<T: fmt::Display> fmt::Display for FixedGrid<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         for (idx, item) in self.inner.iter().enumerate() {
             if idx != 0 && idx % self.width == 0 {
-                writeln!(f, "")?
+                writeln!(f)?
             }
             write!(f, "{}", item)?
         }
@@ -183,7 +183,7 @@ mod tests {
 
     #[test]
     fn build_empty_ascii_grid() {
-        let g = FixedGrid::parse_ascii_grid("", |c| Ok(c)).unwrap();
+        let g = FixedGrid::parse_ascii_grid("", Ok).unwrap();
         assert_eq!(g.width, 0);
     }
 
@@ -0,0 +1,432 @@
+use super::{fixed_grid::FixedGrid, grid_types::GridWidth, pointmap_grid::PointMap};
+use crate::grid::point::Point;
+use std::io::{self, Write};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    pub const BLACK: Rgb = Rgb(0, 0, 0);
+    pub const WHITE: Rgb = Rgb(255, 255, 255);
+
+    pub const fn gray(v: u8) -> Rgb {
+        Rgb(v, v, v)
+    }
+}
+
+/// A pixel format that can be written as a PNG scanline.
+pub trait Pixel: Copy {
+    const COLOR_TYPE: u8;
+    const CHANNELS: usize;
+    fn extend_bytes(self, out: &mut Vec<u8>);
+}
+
+impl Pixel for Rgb {
+    const COLOR_TYPE: u8 = 2;
+    const CHANNELS: usize = 3;
+    fn extend_bytes(self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&[self.0, self.1, self.2]);
+    }
+}
+
+impl Pixel for u8 {
+    const COLOR_TYPE: u8 = 0;
+    const CHANNELS: usize = 1;
+    fn extend_bytes(self, out: &mut Vec<u8>) {
+        out.push(self);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image<P> {
+    width: usize,
+    height: usize,
+    pixels: Vec<P>,
+}
+
+impl<P: Copy> Image<P> {
+    pub fn new(width: usize, height: usize, fill: P) -> Image<P> {
+        Image {
+            width,
+            height,
+            pixels: vec![fill; width * height],
+        }
+    }
+
+    pub fn from_fn<F: FnMut(usize, usize) -> P>(width: usize, height: usize, mut f: F) -> Image<P> {
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                pixels.push(f(x, y));
+            }
+        }
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    fn empty() -> Image<P> {
+        Image {
+            width: 0,
+            height: 0,
+            pixels: Vec::new(),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    pub fn get(&self, x: usize, y: usize) -> P {
+        debug_assert!(x < self.width);
+        self.pixels[y * self.width + x]
+    }
+    pub fn set(&mut self, x: usize, y: usize, p: P) {
+        debug_assert!(x < self.width);
+        self.pixels[y * self.width + x] = p;
+    }
+    pub fn rows(&self) -> impl Iterator<Item = &[P]> + '_ {
+        // chunks panics on 0, and a zero width image has no rows anyway
+        self.pixels.chunks(self.width.max(1))
+    }
+
+    /// Blow each pixel up into a `factor` x `factor` square.
+    pub fn scale(&self, factor: usize) -> Image<P> {
+        if factor <= 1 {
+            return self.clone();
+        }
+        Image::from_fn(self.width * factor, self.height * factor, |x, y| {
+            self.get(x / factor, y / factor)
+        })
+    }
+
+    fn scaled(self, factor: Option<usize>) -> Image<P> {
+        match factor {
+            Some(f) if f > 1 => self.scale(f),
+            _ => self,
+        }
+    }
+}
+
+impl Image<Rgb> {
+    /// Binary (P6) portable pixmap
+    pub fn write_ppm<W: Write>(&self, mut w: W) -> io::Result<()> {
+        write!(w, "P6\n{} {}\n255\n", self.width, self.height)?;
+        let mut buf = Vec::with_capacity(self.pixels.len() * 3);
+        for p in &self.pixels {
+            p.extend_bytes(&mut buf);
+        }
+        w.write_all(&buf)
+    }
+}
+
+impl Image<u8> {
+    /// Binary (P5) portable graymap
+    pub fn write_pgm<W: Write>(&self, mut w: W) -> io::Result<()> {
+        write!(w, "P5\n{} {}\n255\n", self.width, self.height)?;
+        w.write_all(&self.pixels)
+    }
+}
+
+impl<P: Pixel> Image<P> {
+    /// PNG using only "stored" deflate blocks, so no compression library is needed.
+    pub fn write_png<W: Write>(&self, mut w: W) -> io::Result<()> {
+        if self.width == 0 || self.height == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "png images must have a non-zero width and height",
+            ));
+        }
+        w.write_all(&png::SIGNATURE)?;
+
+        let mut ihdr = Vec::with_capacity(13);
+        ihdr.extend_from_slice(&(self.width as u32).to_be_bytes());
+        ihdr.extend_from_slice(&(self.height as u32).to_be_bytes());
+        // bit depth, color type, compression, filter, interlace
+        ihdr.extend_from_slice(&[8, P::COLOR_TYPE, 0, 0, 0]);
+        png::write_chunk(&mut w, b"IHDR", &ihdr)?;
+
+        let mut raw = Vec::with_capacity(self.height * (1 + self.width * P::CHANNELS));
+        for row in self.rows() {
+            // filter type: none
+            raw.push(0);
+            for p in row {
+                p.extend_bytes(&mut raw);
+            }
+        }
+        png::write_chunk(&mut w, b"IDAT", &png::zlib_stored(&raw))?;
+        png::write_chunk(&mut w, b"IEND", &[])
+    }
+}
+
+mod png {
+    use std::io::{self, Write};
+
+    pub(super) const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+    const MAX_STORED_BLOCK: usize = 0xffff;
+
+    pub(super) fn write_chunk<W: Write>(w: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+        w.write_all(&(data.len() as u32).to_be_bytes())?;
+        w.write_all(kind)?;
+        w.write_all(data)?;
+        let crc = crc32_update(crc32_update(0xffff_ffff, kind), data) ^ 0xffff_ffff;
+        w.write_all(&crc.to_be_bytes())
+    }
+
+    pub(super) fn zlib_stored(data: &[u8]) -> Vec<u8> {
+        let blocks = data.len() / MAX_STORED_BLOCK + 1;
+        let mut out = Vec::with_capacity(data.len() + blocks * 5 + 6);
+        // CMF/FLG: deflate with a 32k window, no dictionary, fastest
+        out.extend_from_slice(&[0x78, 0x01]);
+        let mut chunks = data.chunks(MAX_STORED_BLOCK).peekable();
+        if chunks.peek().is_none() {
+            out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+        }
+        while let Some(chunk) = chunks.next() {
+            let last = chunks.peek().is_none();
+            let len = chunk.len() as u16;
+            out.push(last as u8);
+            out.extend_from_slice(&len.to_le_bytes());
+            out.extend_from_slice(&(!len).to_le_bytes());
+            out.extend_from_slice(chunk);
+        }
+        out.extend_from_slice(&adler32(data).to_be_bytes());
+        out
+    }
+
+    pub(super) fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+        for b in data {
+            crc ^= *b as u32;
+            for _ in 0..8 {
+                let mask = (crc & 1).wrapping_neg();
+                crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+            }
+        }
+        crc
+    }
+
+    pub(super) fn adler32(data: &[u8]) -> u32 {
+        const MOD: u32 = 65521;
+        let (mut a, mut b) = (1u32, 0u32);
+        for chunk in data.chunks(5552) {
+            for x in chunk {
+                a += *x as u32;
+                b += a;
+            }
+            a %= MOD;
+            b %= MOD;
+        }
+        (b << 16) | a
+    }
+}
+
+impl<T> FixedGrid<T> {
+    pub fn to_image<P: Copy, F: Fn(&T) -> P>(&self, color: F) -> Image<P> {
+        let width: usize = self.width();
+        if width == 0 {
+            return Image::empty();
+        }
+        Image {
+            width,
+            height: self.height(),
+            pixels: self.raw_iter().map(color).collect(),
+        }
+    }
+
+    pub fn write_ppm<W, F>(&self, w: W, scale: Option<usize>, color: F) -> io::Result<()>
+    where
+        W: Write,
+        F: Fn(&T) -> Rgb,
+    {
+        self.to_image(color).scaled(scale).write_ppm(w)
+    }
+
+    pub fn write_pgm<W, F>(&self, w: W, scale: Option<usize>, color: F) -> io::Result<()>
+    where
+        W: Write,
+        F: Fn(&T) -> u8,
+    {
+        self.to_image(color).scaled(scale).write_pgm(w)
+    }
+
+    pub fn write_png<W, P, F>(&self, w: W, scale: Option<usize>, color: F) -> io::Result<()>
+    where
+        W: Write,
+        P: Pixel,
+        F: Fn(&T) -> P,
+    {
+        self.to_image(color).scaled(scale).write_png(w)
+    }
+}
+
+impl<T> PointMap<T> {
+    /// Cells inside the bounds without an entry are passed to `color` as `None`.
+    pub fn to_image<P: Copy, F: Fn(Option<&T>) -> P>(&self, color: F) -> Image<P> {
+        let bounds = match self.bounds() {
+            Some(b) => b,
+            None => return Image::empty(),
+        };
+        let width = (bounds.max_x - bounds.min_x + 1) as usize;
+        let height = (bounds.max_y - bounds.min_y + 1) as usize;
+        Image::from_fn(width, height, |x, y| {
+            let p = Point::new(bounds.min_x + x as i64, bounds.min_y + y as i64);
+            color(self.get(&p))
+        })
+    }
+
+    pub fn write_ppm<W, F>(&self, w: W, scale: Option<usize>, color: F) -> io::Result<()>
+    where
+        W: Write,
+        F: Fn(Option<&T>) -> Rgb,
+    {
+        self.to_image(color).scaled(scale).write_ppm(w)
+    }
+
+    pub fn write_pgm<W, F>(&self, w: W, scale: Option<usize>, color: F) -> io::Result<()>
+    where
+        W: Write,
+        F: Fn(Option<&T>) -> u8,
+    {
+        self.to_image(color).scaled(scale).write_pgm(w)
+    }
+
+    pub fn write_png<W, P, F>(&self, w: W, scale: Option<usize>, color: F) -> io::Result<()>
+    where
+        W: Write,
+        P: Pixel,
+        F: Fn(Option<&T>) -> P,
+    {
+        self.to_image(color).scaled(scale).write_png(w)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checkers() -> FixedGrid<bool> {
+        FixedGrid::parse_ascii_grid("#.#\n.#.", |c| Ok(c == '#')).unwrap()
+    }
+
+    fn bw(b: &bool) -> Rgb {
+        if *b {
+            Rgb::WHITE
+        } else {
+            Rgb::BLACK
+        }
+    }
+
+    /// Undo the PNG container and stored-deflate encoding, returning (IHDR, raw scanlines)
+    fn decode_png(data: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        assert_eq!(&data[..8], &png::SIGNATURE);
+        let mut rest = &data[8..];
+        let mut ihdr = Vec::new();
+        let mut idat = Vec::new();
+        while !rest.is_empty() {
+            let len = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+            let kind = &rest[4..8];
+            let body = &rest[8..8 + len];
+            let crc =
+                u32::from_be_bytes([rest[8 + len], rest[9 + len], rest[10 + len], rest[11 + len]]);
+            let expected = png::crc32_update(png::crc32_update(0xffff_ffff, kind), body);
+            assert_eq!(crc, expected ^ 0xffff_ffff);
+            match kind {
+                b"IHDR" => ihdr = body.to_vec(),
+                b"IDAT" => idat.extend_from_slice(body),
+                _ => {}
+            }
+            rest = &rest[12 + len..];
+        }
+
+        assert_eq!(&idat[..2], &[0x78, 0x01]);
+        let mut raw = Vec::new();
+        let mut pos = 2;
+        loop {
+            let last = idat[pos] & 1 == 1;
+            let len = u16::from_le_bytes([idat[pos + 1], idat[pos + 2]]) as usize;
+            let nlen = u16::from_le_bytes([idat[pos + 3], idat[pos + 4]]) as usize;
+            assert_eq!(len, !nlen & 0xffff);
+            raw.extend_from_slice(&idat[pos + 5..pos + 5 + len]);
+            pos += 5 + len;
+            if last {
+                break;
+            }
+        }
+        let adler = u32::from_be_bytes([idat[pos], idat[pos + 1], idat[pos + 2], idat[pos + 3]]);
+        assert_eq!(adler, png::adler32(&raw));
+        (ihdr, raw)
+    }
+
+    #[test]
+    fn ppm_from_fixed_grid() {
+        let mut out = Vec::new();
+        checkers().write_ppm(&mut out, None, bw).unwrap();
+        let mut expected = b"P6\n3 2\n255\n".to_vec();
+        for lit in &[true, false, true, false, true, false] {
+            expected.extend_from_slice(if *lit { &[255; 3] } else { &[0; 3] });
+        }
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn pgm_scaled() {
+        let mut out = Vec::new();
+        checkers()
+            .write_pgm(&mut out, Some(2), |b| if *b { 255 } else { 0 })
+            .unwrap();
+        let mut expected = b"P5\n6 4\n255\n".to_vec();
+        expected.extend_from_slice(&[255, 255, 0, 0, 255, 255]);
+        expected.extend_from_slice(&[255, 255, 0, 0, 255, 255]);
+        expected.extend_from_slice(&[0, 0, 255, 255, 0, 0]);
+        expected.extend_from_slice(&[0, 0, 255, 255, 0, 0]);
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn pointmap_image_fills_gaps() {
+        let mut pm = PointMap::default();
+        pm.insert(Point::new(-1, 5), 'a');
+        pm.insert(Point::new(1, 6), 'b');
+        let img = pm.to_image(|c| match c {
+            Some('a') => 1u8,
+            Some(_) => 2,
+            None => 0,
+        });
+        assert_eq!(img.width(), 3);
+        assert_eq!(img.height(), 2);
+        assert_eq!(img.rows().collect::<Vec<_>>(), vec![&[1, 0, 0], &[0, 0, 2]]);
+    }
+
+    #[test]
+    fn png_roundtrip() {
+        let mut out = Vec::new();
+        checkers().write_png(&mut out, None, bw).unwrap();
+        let (ihdr, raw) = decode_png(&out);
+        assert_eq!(ihdr, vec![0, 0, 0, 3, 0, 0, 0, 2, 8, 2, 0, 0, 0]);
+        let mut expected = vec![0];
+        expected.extend_from_slice(&[255, 255, 255, 0, 0, 0, 255, 255, 255]);
+        expected.push(0);
+        expected.extend_from_slice(&[0, 0, 0, 255, 255, 255, 0, 0, 0]);
+        assert_eq!(raw, expected);
+    }
+
+    #[test]
+    fn png_spans_multiple_stored_blocks() {
+        let img = Image::from_fn(300, 300, |x, y| (x ^ y) as u8);
+        let mut out = Vec::new();
+        img.write_png(&mut out).unwrap();
+        let (_, raw) = decode_png(&out);
+        assert_eq!(raw.len(), 300 * 301);
+        assert_eq!(raw[301 + 1 + 7], 1 ^ 7);
+    }
+
+    #[test]
+    fn png_rejects_empty() {
+        let pm = PointMap::<u8>::default();
+        assert!(pm.write_png(Vec::new(), None, |_| 0u8).is_err());
+    }
+}
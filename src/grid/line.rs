@@ -0,0 +1,213 @@
+use super::coordinates::SignedCoordinate;
+use crate::grid::point::Point;
+
+impl<T: SignedCoordinate> Point<T> {
+    /// Every point from `self` to `end` inclusive, for horizontal, vertical
+    /// and 45 degree lines only.
+    pub fn line_to(self, end: Point<T>) -> anyhow::Result<LineIter<T>> {
+        let dx = end.x - self.x;
+        let dy = end.y - self.y;
+        if dx != T::zero() && dy != T::zero() && dx.abs() != dy.abs() {
+            anyhow::bail!("line is not horizontal, vertical or diagonal");
+        }
+        Ok(LineIter {
+            next: Some(self),
+            end,
+            step: Point::new(dx.signum(), dy.signum()),
+        })
+    }
+
+    /// Every point from `self` to `end` inclusive, for any slope.
+    pub fn bresenham_to(self, end: Point<T>) -> BresenhamIter<T> {
+        let dx = (end.x - self.x).abs();
+        let dy = -(end.y - self.y).abs();
+        BresenhamIter {
+            next: Some(self),
+            end,
+            step: Point::new((end.x - self.x).signum(), (end.y - self.y).signum()),
+            delta: Point::new(dx, dy),
+            err: dx + dy,
+        }
+    }
+}
+
+pub struct LineIter<T> {
+    next: Option<Point<T>>,
+    end: Point<T>,
+    step: Point<T>,
+}
+
+impl<T: SignedCoordinate> Iterator for LineIter<T> {
+    type Item = Point<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next.take()?;
+        if current != self.end {
+            self.next = Some(current + self.step);
+        }
+        Some(current)
+    }
+}
+
+pub struct BresenhamIter<T> {
+    next: Option<Point<T>>,
+    end: Point<T>,
+    step: Point<T>,
+    delta: Point<T>,
+    err: T,
+}
+
+impl<T: SignedCoordinate> Iterator for BresenhamIter<T> {
+    type Item = Point<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next.take()?;
+        if current != self.end {
+            let mut next = current;
+            let e2 = self.err + self.err;
+            if e2 >= self.delta.y {
+                self.err = self.err + self.delta.y;
+                next.x = next.x + self.step.x;
+            }
+            if e2 <= self.delta.x {
+                self.err = self.err + self.delta.x;
+                next.y = next.y + self.step.y;
+            }
+            self.next = Some(next);
+        }
+        Some(current)
+    }
+}
+
+/// Walks a chain of straight segments, yielding each shared corner once.
+pub struct PolylineIter<T> {
+    vertices: Vec<Point<T>>,
+    segment: usize,
+    current: Option<LineIter<T>>,
+}
+
+impl<T: SignedCoordinate> PolylineIter<T> {
+    /// Every segment must be horizontal, vertical or diagonal, see [`Point::line_to`].
+    pub fn new<I: IntoIterator<Item = Point<T>>>(points: I) -> anyhow::Result<PolylineIter<T>> {
+        let vertices = points.into_iter().collect::<Vec<_>>();
+        for (idx, pair) in vertices.windows(2).enumerate() {
+            pair[0]
+                .line_to(pair[1])
+                .map_err(|e| anyhow::anyhow!("segment {}: {}", idx, e))?;
+        }
+        let current = match vertices.as_slice() {
+            [single] => single.line_to(*single).ok(),
+            _ => None,
+        };
+        Ok(PolylineIter {
+            vertices,
+            segment: 0,
+            current,
+        })
+    }
+}
+
+impl<T: SignedCoordinate> Iterator for PolylineIter<T> {
+    type Item = Point<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(p) = self.current.as_mut().and_then(|l| l.next()) {
+                return Some(p);
+            }
+            if self.segment + 1 >= self.vertices.len() {
+                return None;
+            }
+            let start = self.vertices[self.segment];
+            let end = self.vertices[self.segment + 1];
+            let mut line = start
+                .line_to(end)
+                .expect("segments are validated on construction");
+            if self.segment > 0 {
+                // the previous segment already produced this corner
+                line.next();
+            }
+            self.segment += 1;
+            self.current = Some(line);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pts(v: &[(i64, i64)]) -> Vec<Point<i64>> {
+        v.iter().map(Point::from).collect()
+    }
+
+    #[test]
+    fn straight_lines() {
+        let p = Point::new(1i64, 1);
+        assert_eq!(
+            p.line_to(Point::new(1, 3)).unwrap().collect::<Vec<_>>(),
+            pts(&[(1, 1), (1, 2), (1, 3)])
+        );
+        assert_eq!(
+            p.line_to(Point::new(-1, 1)).unwrap().collect::<Vec<_>>(),
+            pts(&[(1, 1), (0, 1), (-1, 1)])
+        );
+        assert_eq!(
+            p.line_to(Point::new(3, -1)).unwrap().collect::<Vec<_>>(),
+            pts(&[(1, 1), (2, 0), (3, -1)])
+        );
+        assert_eq!(p.line_to(p).unwrap().collect::<Vec<_>>(), vec![p]);
+    }
+
+    #[test]
+    fn bad_slope() {
+        assert!(Point::new(0i32, 0).line_to(Point::new(2, 1)).is_err());
+    }
+
+    #[test]
+    fn bresenham() {
+        let line = Point::new(0i32, 0).bresenham_to(Point::new(6, 3));
+        assert_eq!(
+            line.map(|p| (p.x, p.y)).collect::<Vec<_>>(),
+            vec![(0, 0), (1, 1), (2, 1), (3, 2), (4, 2), (5, 3), (6, 3)]
+        );
+        let line = Point::new(2i64, 5).bresenham_to(Point::new(0, 0));
+        let line = line.collect::<Vec<_>>();
+        assert_eq!(line.len(), 6);
+        assert_eq!(line.first(), Some(&Point::new(2, 5)));
+        assert_eq!(line.last(), Some(&Point::new(0, 0)));
+    }
+
+    #[test]
+    fn bresenham_matches_straight_lines() {
+        let start = Point::new(3i64, -2);
+        for end in &[(3, 4), (-5, -2), (7, 2), (0, 1)] {
+            let end = Point::from(end);
+            assert_eq!(
+                start.bresenham_to(end).collect::<Vec<_>>(),
+                start.line_to(end).unwrap().collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn rock_path() {
+        // 498,4 -> 498,6 -> 496,6
+        let path = PolylineIter::new(pts(&[(498, 4), (498, 6), (496, 6)])).unwrap();
+        assert_eq!(
+            path.collect::<Vec<_>>(),
+            pts(&[(498, 4), (498, 5), (498, 6), (497, 6), (496, 6)])
+        );
+        let single = PolylineIter::new(pts(&[(1, 1)])).unwrap();
+        assert_eq!(single.collect::<Vec<_>>(), pts(&[(1, 1)]));
+        assert_eq!(PolylineIter::<i64>::new(vec![]).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn polyline_rejects_bad_segment() {
+        let err = PolylineIter::new(pts(&[(0, 0), (0, 5), (2, 4)]))
+            .err()
+            .unwrap();
+        assert!(err.to_string().starts_with("segment 1"));
+    }
+}
@@ -20,6 +20,9 @@ impl<Cd: Coordinate, T> PointMap<T, Cd> {
     pub fn get(&self, key: &Point<Cd>) -> Option<&T> {
         self.inner.get(key)
     }
+    pub fn iter(&self) -> impl Iterator<Item = (&Point<Cd>, &T)> + '_ {
+        self.inner.iter()
+    }
 }
 
 impl<T> Default for PointMap<T> {
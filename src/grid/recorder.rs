@@ -0,0 +1,495 @@
+use super::{
+    fixed_grid::FixedGrid,
+    image::{Image, Rgb},
+    pointmap_grid::PointMap,
+};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+const GIF_MAX_COLORS: usize = 256;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Palette {
+    /// Collect every color used by the frames, failing if there are more than 256
+    Auto,
+    /// Snap every pixel to the nearest of these colors
+    Fixed(Vec<Rgb>),
+}
+
+/// Collects snapshots of a simulation and writes them out as an animation.
+///
+/// Frames of different sizes are anchored at the top left and padded with
+/// the background color up to the largest frame.
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    frames: Vec<Image<Rgb>>,
+    fps: u32,
+    scale: usize,
+    palette: Palette,
+    background: Rgb,
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Recorder {
+            frames: Vec::new(),
+            fps: 10,
+            scale: 1,
+            palette: Palette::Auto,
+            background: Rgb::BLACK,
+        }
+    }
+}
+
+impl Recorder {
+    pub fn new() -> Recorder {
+        Recorder::default()
+    }
+
+    pub fn fps(mut self, fps: u32) -> Recorder {
+        self.fps = fps.max(1);
+        self
+    }
+    pub fn scale(mut self, scale: usize) -> Recorder {
+        self.scale = scale.max(1);
+        self
+    }
+    pub fn palette(mut self, palette: Palette) -> Recorder {
+        self.palette = palette;
+        self
+    }
+    pub fn background(mut self, background: Rgb) -> Recorder {
+        self.background = background;
+        self
+    }
+
+    pub fn push(&mut self, frame: Image<Rgb>) {
+        self.frames.push(frame.scale(self.scale));
+    }
+    pub fn record_grid<T, F: Fn(&T) -> Rgb>(&mut self, grid: &FixedGrid<T>, color: F) {
+        self.push(grid.to_image(color))
+    }
+    pub fn record_points<T, F: Fn(Option<&T>) -> Rgb>(&mut self, grid: &PointMap<T>, color: F) {
+        self.push(grid.to_image(color))
+    }
+
+    pub fn frames(&self) -> &[Image<Rgb>] {
+        self.frames.as_slice()
+    }
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    fn frame_delay(&self) -> Duration {
+        Duration::from_millis(1000 / self.fps as u64)
+    }
+
+    fn canvas_size(&self) -> (usize, usize) {
+        self.frames
+            .iter()
+            .fold((0, 0), |(w, h), f| (w.max(f.width()), h.max(f.height())))
+    }
+
+    fn pixel_at(&self, frame: &Image<Rgb>, x: usize, y: usize) -> Rgb {
+        if x < frame.width() && y < frame.height() {
+            frame.get(x, y)
+        } else {
+            self.background
+        }
+    }
+
+    /// Write every frame as `{prefix}00000.ppm`, `{prefix}00001.ppm`, ...
+    pub fn write_ppm_frames<P: AsRef<Path>>(
+        &self,
+        dir: P,
+        prefix: &str,
+    ) -> io::Result<Vec<PathBuf>> {
+        let (width, height) = self.canvas_size();
+        let mut paths = Vec::with_capacity(self.frames.len());
+        for (idx, frame) in self.frames.iter().enumerate() {
+            let path = dir.as_ref().join(format!("{}{:05}.ppm", prefix, idx));
+            let padded = Image::from_fn(width, height, |x, y| self.pixel_at(frame, x, y));
+            padded.write_ppm(BufWriter::new(File::create(&path)?))?;
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+
+    fn build_palette(&self) -> anyhow::Result<Vec<Rgb>> {
+        match &self.palette {
+            Palette::Fixed(colors) => {
+                if colors.is_empty() || colors.len() > GIF_MAX_COLORS {
+                    anyhow::bail!(
+                        "palette must have between 1 and {} colors, found {}",
+                        GIF_MAX_COLORS,
+                        colors.len()
+                    );
+                }
+                Ok(colors.clone())
+            }
+            Palette::Auto => {
+                let mut colors = vec![self.background];
+                for frame in &self.frames {
+                    for row in frame.rows() {
+                        for p in row {
+                            if !colors.contains(p) {
+                                colors.push(*p);
+                                if colors.len() > GIF_MAX_COLORS {
+                                    anyhow::bail!(
+                                        "frames use more than {} colors, provide a fixed palette",
+                                        GIF_MAX_COLORS
+                                    );
+                                }
+                            }
+                        }
+                    }
+                }
+                Ok(colors)
+            }
+        }
+    }
+
+    /// Write an infinitely looping GIF animation.
+    pub fn write_gif<W: Write>(&self, mut w: W) -> anyhow::Result<()> {
+        let (width, height) = self.canvas_size();
+        if width > u16::MAX as usize || height > u16::MAX as usize {
+            anyhow::bail!("gif dimensions {}x{} are too large", width, height);
+        }
+        let palette = self.build_palette()?;
+        let mut lookup = HashMap::new();
+        let mut index_of = |p: Rgb| -> u8 {
+            *lookup
+                .entry(p)
+                .or_insert_with(|| nearest_color(&palette, p) as u8)
+        };
+
+        // smallest power of two that holds the palette, gif requires at least 2 entries
+        let table_bits = (1..=8).find(|b| 1 << b >= palette.len()).unwrap_or(8);
+        let min_code_size = table_bits.max(2) as u8;
+
+        w.write_all(b"GIF89a")?;
+        w.write_all(&(width as u16).to_le_bytes())?;
+        w.write_all(&(height as u16).to_le_bytes())?;
+        // global color table present, 8 bit color resolution, table size
+        w.write_all(&[0xf0 | (table_bits as u8 - 1), 0, 0])?;
+        for idx in 0..(1 << table_bits) {
+            let Rgb(r, g, b) = palette.get(idx).copied().unwrap_or_default();
+            w.write_all(&[r, g, b])?;
+        }
+        // NETSCAPE2.0 extension, loop forever
+        w.write_all(&[0x21, 0xff, 0x0b])?;
+        w.write_all(b"NETSCAPE2.0")?;
+        w.write_all(&[0x03, 0x01, 0x00, 0x00, 0x00])?;
+
+        let delay = (100 / self.fps).max(1) as u16;
+        for frame in &self.frames {
+            // graphic control extension, disposal: leave in place
+            w.write_all(&[0x21, 0xf9, 0x04, 0x04])?;
+            w.write_all(&delay.to_le_bytes())?;
+            w.write_all(&[0x00, 0x00])?;
+
+            w.write_all(&[0x2c, 0, 0, 0, 0])?;
+            w.write_all(&(width as u16).to_le_bytes())?;
+            w.write_all(&(height as u16).to_le_bytes())?;
+            w.write_all(&[0x00])?;
+
+            let mut indices = Vec::with_capacity(width * height);
+            for y in 0..height {
+                for x in 0..width {
+                    indices.push(index_of(self.pixel_at(frame, x, y)));
+                }
+            }
+            w.write_all(&[min_code_size])?;
+            for block in lzw::encode(min_code_size, &indices).chunks(255) {
+                w.write_all(&[block.len() as u8])?;
+                w.write_all(block)?;
+            }
+            w.write_all(&[0x00])?;
+        }
+        w.write_all(&[0x3b])?;
+        Ok(())
+    }
+
+    /// Animate the frames in a terminal with 24 bit color, two pixels per character cell.
+    pub fn play<W: Write>(&self, mut w: W) -> io::Result<()> {
+        write!(w, "\x1b[2J\x1b[?25l")?;
+        for frame in &self.frames {
+            write!(w, "\x1b[H")?;
+            self.draw_frame(&mut w, frame)?;
+            w.flush()?;
+            thread::sleep(self.frame_delay());
+        }
+        write!(w, "\x1b[?25h")?;
+        w.flush()
+    }
+
+    fn draw_frame<W: Write>(&self, w: &mut W, frame: &Image<Rgb>) -> io::Result<()> {
+        let (width, height) = self.canvas_size();
+        for y in (0..height).step_by(2) {
+            for x in 0..width {
+                let Rgb(tr, tg, tb) = self.pixel_at(frame, x, y);
+                let Rgb(br, bg, bb) = self.pixel_at(frame, x, y + 1);
+                write!(
+                    w,
+                    "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m\u{2580}",
+                    tr, tg, tb, br, bg, bb
+                )?;
+            }
+            writeln!(w, "\x1b[0m")?;
+        }
+        Ok(())
+    }
+}
+
+fn nearest_color(palette: &[Rgb], p: Rgb) -> usize {
+    let dist = |c: &Rgb| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(c.0, p.0) + d(c.1, p.1) + d(c.2, p.2)
+    };
+    palette
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, c)| dist(c))
+        .map(|(idx, _)| idx)
+        .unwrap_or(0)
+}
+
+mod lzw {
+    use std::collections::HashMap;
+
+    const MAX_CODES: u16 = 4096;
+    const MAX_CODE_SIZE: u32 = 12;
+
+    struct BitWriter {
+        out: Vec<u8>,
+        acc: u32,
+        bits: u32,
+    }
+
+    impl BitWriter {
+        fn write(&mut self, code: u16, size: u32) {
+            self.acc |= (code as u32) << self.bits;
+            self.bits += size;
+            while self.bits >= 8 {
+                self.out.push(self.acc as u8);
+                self.acc >>= 8;
+                self.bits -= 8;
+            }
+        }
+        fn finish(mut self) -> Vec<u8> {
+            if self.bits > 0 {
+                self.out.push(self.acc as u8);
+            }
+            self.out
+        }
+    }
+
+    /// Variable width LSB-first LZW, as used by GIF image data.
+    pub(super) fn encode(min_code_size: u8, data: &[u8]) -> Vec<u8> {
+        let clear = 1u16 << min_code_size;
+        let eoi = clear + 1;
+        let reset_size = min_code_size as u32 + 1;
+
+        let mut w = BitWriter {
+            out: Vec::new(),
+            acc: 0,
+            bits: 0,
+        };
+        let mut dict: HashMap<(u16, u8), u16> = HashMap::new();
+        let mut next = eoi + 1;
+        let mut size = reset_size;
+
+        w.write(clear, size);
+        let mut iter = data.iter();
+        let mut prefix = match iter.next() {
+            Some(k) => *k as u16,
+            None => {
+                w.write(eoi, size);
+                return w.finish();
+            }
+        };
+        for k in iter {
+            if let Some(code) = dict.get(&(prefix, *k)) {
+                prefix = *code;
+                continue;
+            }
+            w.write(prefix, size);
+            if next < MAX_CODES {
+                dict.insert((prefix, *k), next);
+                next += 1;
+                if next > (1 << size) && size < MAX_CODE_SIZE {
+                    size += 1;
+                }
+            } else {
+                w.write(clear, size);
+                dict.clear();
+                next = eoi + 1;
+                size = reset_size;
+            }
+            prefix = *k as u16;
+        }
+        w.write(prefix, size);
+        // the decoder adds an entry after reading the final code too, and may widen for it
+        if next == (1 << size) && size < MAX_CODE_SIZE {
+            size += 1;
+        }
+        w.write(eoi, size);
+        w.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_lzw(min_code_size: u8, data: &[u8]) -> Vec<u8> {
+        let clear = 1usize << min_code_size;
+        let eoi = clear + 1;
+        let mut size = min_code_size as usize + 1;
+        let mut table: Vec<Vec<u8>> = Vec::new();
+        let reset = |table: &mut Vec<Vec<u8>>| {
+            table.clear();
+            table.extend((0..clear).map(|i| vec![i as u8]));
+            table.push(Vec::new());
+            table.push(Vec::new());
+        };
+        reset(&mut table);
+
+        let mut out = Vec::new();
+        let mut prev: Option<usize> = None;
+        let (mut acc, mut bits, mut pos) = (0usize, 0usize, 0usize);
+        loop {
+            while bits < size {
+                acc |= (data[pos] as usize) << bits;
+                pos += 1;
+                bits += 8;
+            }
+            let code = acc & ((1 << size) - 1);
+            acc >>= size;
+            bits -= size;
+
+            if code == clear {
+                reset(&mut table);
+                size = min_code_size as usize + 1;
+                prev = None;
+                continue;
+            }
+            if code == eoi {
+                return out;
+            }
+            let entry = match prev {
+                None => table[code].clone(),
+                Some(p) => {
+                    let entry = if code < table.len() {
+                        table[code].clone()
+                    } else {
+                        let mut e = table[p].clone();
+                        e.push(table[p][0]);
+                        e
+                    };
+                    let mut added = table[p].clone();
+                    added.push(entry[0]);
+                    table.push(added);
+                    if table.len() == 1 << size && size < 12 {
+                        size += 1;
+                    }
+                    entry
+                }
+            };
+            out.extend_from_slice(&entry);
+            prev = Some(code);
+        }
+    }
+
+    #[test]
+    fn lzw_roundtrip_small() {
+        let data = vec![0, 0, 0, 1, 1, 1, 0, 0, 0, 1, 1, 1, 2, 3, 2, 3];
+        let enc = lzw::encode(2, &data);
+        assert_eq!(decode_lzw(2, &enc), data);
+    }
+
+    #[test]
+    fn lzw_roundtrip_table_reset() {
+        // enough pseudo random data to fill the 4096 entry table several times
+        let mut state = 12345u32;
+        let data = (0..50_000)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                ((state >> 16) % 200) as u8
+            })
+            .collect::<Vec<_>>();
+        let enc = lzw::encode(8, &data);
+        assert_eq!(decode_lzw(8, &enc), data);
+    }
+
+    #[test]
+    fn gif_structure() {
+        let mut rec = Recorder::new().fps(25);
+        let mut g = FixedGrid::<bool>::from_dimm(2, 3);
+        rec.record_grid(&g, |b| if *b { Rgb::WHITE } else { Rgb::BLACK });
+        g[crate::Point::new(1i64, 1)] = true;
+        rec.record_grid(&g, |b| if *b { Rgb::WHITE } else { Rgb::BLACK });
+
+        let mut out = Vec::new();
+        rec.write_gif(&mut out).unwrap();
+        assert_eq!(&out[..6], b"GIF89a");
+        assert_eq!(&out[6..10], &[3, 0, 2, 0]);
+        // two entry global color table
+        assert_eq!(out[10] & 0x07, 0);
+        assert_eq!(&out[13..19], &[0, 0, 0, 255, 255, 255]);
+        assert_eq!(*out.last().unwrap(), 0x3b);
+        // delay of 4 centiseconds in each graphic control extension
+        let gce = out
+            .windows(6)
+            .filter(|w| w[..4] == [0x21, 0xf9, 0x04, 0x04])
+            .map(|w| u16::from_le_bytes([w[4], w[5]]))
+            .collect::<Vec<_>>();
+        assert_eq!(gce, vec![4, 4]);
+    }
+
+    #[test]
+    fn fixed_palette_snaps_to_nearest() {
+        let palette = vec![Rgb::BLACK, Rgb(200, 0, 0)];
+        assert_eq!(nearest_color(&palette, Rgb(250, 10, 10)), 1);
+        assert_eq!(nearest_color(&palette, Rgb(20, 10, 10)), 0);
+    }
+
+    #[test]
+    fn auto_palette_overflow() {
+        let mut rec = Recorder::new();
+        rec.push(Image::from_fn(300, 1, |x, _| {
+            Rgb(x as u8, (x >> 8) as u8, 0)
+        }));
+        assert!(rec.write_gif(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn frames_padded_to_canvas() {
+        let mut rec = Recorder::new().background(Rgb(1, 2, 3));
+        rec.push(Image::new(1, 1, Rgb::WHITE));
+        rec.push(Image::new(2, 3, Rgb::BLACK));
+        assert_eq!(rec.canvas_size(), (2, 3));
+        assert_eq!(rec.pixel_at(&rec.frames()[0], 1, 2), Rgb(1, 2, 3));
+    }
+
+    #[test]
+    fn play_draws_half_blocks() {
+        let mut rec = Recorder::new().fps(1000);
+        rec.push(Image::from_fn(1, 2, |_, y| Rgb::gray(y as u8)));
+        let mut out = Vec::new();
+        rec.play(&mut out).unwrap();
+        let s = String::from_utf8(out).unwrap();
+        assert!(s.starts_with("\x1b[2J\x1b[?25l\x1b[H"));
+        assert!(s.contains("\x1b[38;2;0;0;0m\x1b[48;2;1;1;1m\u{2580}\x1b[0m\n"));
+        assert!(s.ends_with("\x1b[?25h"));
+    }
+}
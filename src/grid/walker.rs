@@ -0,0 +1,412 @@
+use super::{compass::Direction, fixed_grid::FixedGrid};
+use crate::grid::point::Point;
+
+/// How a walker sees a grid cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tile {
+    Open,
+    Wall,
+    /// Not part of the map at all, walking onto it goes through the edge policy
+    Void,
+}
+
+/// Decides where a walker ends up when it steps off the grid or onto a void cell.
+///
+/// Directions are as on the grid, see [`grid_delta`]. Returning `None`
+/// blocks the step. The destination is still checked for walls by the walker.
+pub trait EdgePolicy<T> {
+    fn wrap(
+        &self,
+        grid: &FixedGrid<T>,
+        tile: &dyn Fn(&T) -> Tile,
+        pos: Point<i64>,
+        dir: Direction,
+    ) -> Option<(Point<i64>, Direction)>;
+}
+
+/// The edge of the map is a wall
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stop;
+
+impl<T> EdgePolicy<T> for Stop {
+    fn wrap(
+        &self,
+        _grid: &FixedGrid<T>,
+        _tile: &dyn Fn(&T) -> Tile,
+        _pos: Point<i64>,
+        _dir: Direction,
+    ) -> Option<(Point<i64>, Direction)> {
+        None
+    }
+}
+
+/// Opposite edges are joined, as on a torus
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Torus;
+
+impl<T> EdgePolicy<T> for Torus {
+    fn wrap(
+        &self,
+        grid: &FixedGrid<T>,
+        _tile: &dyn Fn(&T) -> Tile,
+        pos: Point<i64>,
+        dir: Direction,
+    ) -> Option<(Point<i64>, Direction)> {
+        let width = grid_width(grid);
+        let height = grid.height() as i64;
+        let next = pos + grid_delta(dir);
+        Some((
+            Point::new(next.x.rem_euclid(width), next.y.rem_euclid(height)),
+            dir,
+        ))
+    }
+}
+
+/// Continue from the first non-void cell at the far end of the row or column
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WrapFirstOpen;
+
+impl<T> EdgePolicy<T> for WrapFirstOpen {
+    fn wrap(
+        &self,
+        grid: &FixedGrid<T>,
+        tile: &dyn Fn(&T) -> Tile,
+        pos: Point<i64>,
+        dir: Direction,
+    ) -> Option<(Point<i64>, Direction)> {
+        let step = grid_delta(dir);
+        let mut p = pos;
+        // walk backwards to the last cell on the map, that is where we come back in
+        loop {
+            let back = p + step.scale(-1);
+            match grid.maybe_point_to_idx(back) {
+                Some(idx) if tile(&grid.inner[idx]) != Tile::Void => p = back,
+                _ => return Some((p, dir)),
+            }
+        }
+    }
+}
+
+/// Any other rule, given the position and facing before the step
+pub struct Teleport<F>(pub F);
+
+impl<T, F> EdgePolicy<T> for Teleport<F>
+where
+    F: Fn(Point<i64>, Direction) -> Option<(Point<i64>, Direction)>,
+{
+    fn wrap(
+        &self,
+        _grid: &FixedGrid<T>,
+        _tile: &dyn Fn(&T) -> Tile,
+        pos: Point<i64>,
+        dir: Direction,
+    ) -> Option<(Point<i64>, Direction)> {
+        (self.0)(pos, dir)
+    }
+}
+
+/// The step `dir` takes on a grid.
+///
+/// Grid rows grow downward, so north is -y here, the reverse of
+/// [`Direction::delta`]. That keeps north at the top of the picture and
+/// clockwise turns clockwise on screen.
+pub fn grid_delta(dir: Direction) -> Point<i64> {
+    let d = dir.delta();
+    Point::new(d.x, -d.y)
+}
+
+fn grid_width<T>(grid: &FixedGrid<T>) -> i64 {
+    super::grid_types::GridWidth::<i64>::width(grid)
+}
+
+/// A position and facing that can be moved around like a turtle.
+///
+/// Turning right is clockwise on the compass, see [`Direction::clockwise`].
+/// Every move, with or without a map, follows [`grid_delta`], so north is -y
+/// and `R` is a clockwise turn on a drawn map. Every move and turn is
+/// recorded so the route can be rendered later.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Walker {
+    pos: Point<i64>,
+    facing: Direction,
+    history: Vec<(Point<i64>, Direction)>,
+}
+
+impl Walker {
+    pub fn new(pos: Point<i64>, facing: Direction) -> Walker {
+        Walker {
+            pos,
+            facing,
+            history: vec![(pos, facing)],
+        }
+    }
+
+    pub fn pos(&self) -> Point<i64> {
+        self.pos
+    }
+    pub fn facing(&self) -> Direction {
+        self.facing
+    }
+    /// Every (position, facing) the walker has been in, starting with the first
+    pub fn history(&self) -> &[(Point<i64>, Direction)] {
+        self.history.as_slice()
+    }
+    /// The positions visited, without repeats from turning on the spot
+    pub fn path(&self) -> Vec<Point<i64>> {
+        let mut path = self.history.iter().map(|(p, _)| *p).collect::<Vec<_>>();
+        path.dedup();
+        path
+    }
+
+    fn record(&mut self) {
+        self.history.push((self.pos, self.facing));
+    }
+
+    pub fn turn_left(&mut self) {
+        self.facing = self.facing.anticlockwise();
+        self.record();
+    }
+    pub fn turn_right(&mut self) {
+        self.facing = self.facing.clockwise();
+        self.record();
+    }
+    pub fn turn_around(&mut self) {
+        self.facing = self.facing.clockwise().clockwise();
+        self.record();
+    }
+
+    /// Move without any map to get in the way
+    pub fn forward(&mut self, n: usize) {
+        for _ in 0..n {
+            self.pos += grid_delta(self.facing);
+            self.record();
+        }
+    }
+
+    /// Move up to `n` steps on a grid, returning how many were taken before hitting a wall
+    pub fn forward_on<T, P>(
+        &mut self,
+        grid: &FixedGrid<T>,
+        tile: &dyn Fn(&T) -> Tile,
+        policy: &P,
+        n: usize,
+    ) -> usize
+    where
+        P: EdgePolicy<T> + ?Sized,
+    {
+        for taken in 0..n {
+            let next = self.pos + grid_delta(self.facing);
+            let candidate = match grid.maybe_point_to_idx(next) {
+                Some(idx) if tile(&grid.inner[idx]) != Tile::Void => Some((next, self.facing)),
+                _ => policy.wrap(grid, tile, self.pos, self.facing),
+            };
+            let (next, facing) = match candidate {
+                Some(c) => c,
+                None => return taken,
+            };
+            match grid
+                .maybe_point_to_idx(next)
+                .map(|idx| tile(&grid.inner[idx]))
+            {
+                Some(Tile::Open) => {}
+                _ => return taken,
+            }
+            self.pos = next;
+            self.facing = facing;
+            self.record();
+        }
+        n
+    }
+
+    /// Run instructions like `10R5L5`: numbers move forward, `L`/`R` turn
+    /// and `B` turns around. Whitespace is ignored.
+    pub fn execute(&mut self, instructions: &str) -> anyhow::Result<()> {
+        self.run(instructions, |w, n| w.forward(n))
+    }
+
+    /// Like [`Walker::execute`], moving with [`Walker::forward_on`]
+    pub fn execute_on<T, P>(
+        &mut self,
+        grid: &FixedGrid<T>,
+        tile: &dyn Fn(&T) -> Tile,
+        policy: &P,
+        instructions: &str,
+    ) -> anyhow::Result<()>
+    where
+        P: EdgePolicy<T> + ?Sized,
+    {
+        self.run(instructions, |w, n| {
+            w.forward_on(grid, tile, policy, n);
+        })
+    }
+
+    fn run<F: FnMut(&mut Walker, usize)>(
+        &mut self,
+        instructions: &str,
+        mut step: F,
+    ) -> anyhow::Result<()> {
+        let mut number: Option<usize> = None;
+        for (idx, c) in instructions.chars().enumerate() {
+            if let Some(d) = c.to_digit(10) {
+                number = Some(number.unwrap_or(0) * 10 + d as usize);
+                continue;
+            }
+            if let Some(n) = number.take() {
+                step(self, n);
+            }
+            match c {
+                'L' => self.turn_left(),
+                'R' => self.turn_right(),
+                'B' => self.turn_around(),
+                c if c.is_whitespace() => {}
+                c => anyhow::bail!("unknown instruction {:?} at position {}", c, idx),
+            }
+        }
+        if let Some(n) = number {
+            step(self, n);
+        }
+        Ok(())
+    }
+}
+
+/// The worked example from the monkey map puzzle, for the walker and cube tests
+#[cfg(test)]
+pub(crate) mod monkey_map {
+    use super::*;
+
+    pub(crate) const MONKEY_MAP: &str = "        ...#
+        .#..
+        #...
+        ....
+...#.......#
+........#...
+..#....#....
+..........#.
+        ...#....
+        .....#..
+        .#......
+        ......#.";
+
+    pub(crate) fn parse_monkey_map() -> FixedGrid<char> {
+        let width = MONKEY_MAP.lines().map(|l| l.len()).max().unwrap();
+        let padded = MONKEY_MAP
+            .lines()
+            .map(|l| format!("{:width$}", l, width = width))
+            .collect::<Vec<_>>()
+            .join("\n");
+        FixedGrid::parse_ascii_grid(&padded, Ok).unwrap()
+    }
+
+    pub(crate) fn monkey_tile(c: &char) -> Tile {
+        match c {
+            '.' => Tile::Open,
+            '#' => Tile::Wall,
+            _ => Tile::Void,
+        }
+    }
+
+    pub(crate) fn password(w: &Walker) -> i64 {
+        let facing = match w.facing() {
+            Direction::East => 0,
+            Direction::South => 1,
+            Direction::West => 2,
+            Direction::North => 3,
+        };
+        1000 * (w.pos().y + 1) + 4 * (w.pos().x + 1) + facing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::monkey_map::*;
+    use super::*;
+
+    #[test]
+    fn turtle_moves() {
+        let mut w = Walker::new(Point::new(0, 0), Direction::North);
+        w.execute("2R3 B1L").unwrap();
+        assert_eq!(w.pos(), Point::new(2, -2));
+        assert_eq!(w.facing(), Direction::South);
+        assert_eq!(
+            w.path(),
+            vec![
+                Point::new(0, 0),
+                Point::new(0, -1),
+                Point::new(0, -2),
+                Point::new(1, -2),
+                Point::new(2, -2),
+                Point::new(3, -2),
+                Point::new(2, -2),
+            ]
+        );
+        assert!(w.execute("2X").is_err());
+    }
+
+    #[test]
+    fn stop_and_torus() {
+        let g = FixedGrid::parse_ascii_grid("...\n.#.\n...", Ok).unwrap();
+        let tile = |c: &char| monkey_tile(c);
+
+        let mut w = Walker::new(Point::new(0, 0), Direction::West);
+        assert_eq!(w.forward_on(&g, &tile, &Stop, 5), 0);
+        w.turn_around();
+        assert_eq!(w.forward_on(&g, &tile, &Stop, 5), 2);
+        assert_eq!(w.pos(), Point::new(2, 0));
+
+        assert_eq!(w.forward_on(&g, &tile, &Torus, 4), 4);
+        assert_eq!(w.pos(), Point::new(0, 0));
+        // facing east on the grid, a right turn points down the rows
+        w.turn_right();
+        assert_eq!(w.facing(), Direction::South);
+        assert_eq!(w.forward_on(&g, &tile, &Torus, 4), 4);
+        assert_eq!(w.pos(), Point::new(0, 1));
+    }
+
+    #[test]
+    fn same_frame_with_and_without_a_map() {
+        let g = FixedGrid::parse_ascii_grid("....\n....\n....", Ok).unwrap();
+        let mut on_map = Walker::new(Point::new(0, 0), Direction::East);
+        on_map
+            .execute_on(&g, &|c: &char| monkey_tile(c), &Stop, "2R2L1")
+            .unwrap();
+        let mut free = Walker::new(Point::new(0, 0), Direction::East);
+        free.execute("2R2L1").unwrap();
+        assert_eq!(on_map.history(), free.history());
+        assert_eq!(free.pos(), Point::new(3, 2));
+    }
+
+    #[test]
+    fn teleport() {
+        let g = FixedGrid::parse_ascii_grid("....", Ok).unwrap();
+        let portal = Teleport(|p: Point<i64>, d: Direction| {
+            if p.x == 3 && d == Direction::East {
+                Some((Point::new(1, 0), Direction::West))
+            } else {
+                None
+            }
+        });
+        let mut w = Walker::new(Point::new(2, 0), Direction::East);
+        let taken = w.forward_on(&g, &|c: &char| monkey_tile(c), &portal, 4);
+        assert_eq!(taken, 3);
+        assert_eq!(w.pos(), Point::new(0, 0));
+        assert_eq!(w.facing(), Direction::West);
+    }
+
+    #[test]
+    fn monkey_map_flat() {
+        let g = parse_monkey_map();
+        let start = g
+            .points()
+            .find(|p| monkey_tile(&g[*p]) == Tile::Open)
+            .unwrap();
+        let mut w = Walker::new(start, Direction::East);
+        w.execute_on(
+            &g,
+            &|c: &char| monkey_tile(c),
+            &WrapFirstOpen,
+            "10R5L5R10L4R5L5",
+        )
+        .unwrap();
+        assert_eq!(password(&w), 6032);
+    }
+}
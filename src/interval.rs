@@ -0,0 +1,317 @@
+use crate::grid::coordinates::Zero;
+use std::{
+    fmt,
+    ops::{Add, Range, Sub},
+};
+
+pub trait Bound: Ord + Copy + Zero + Add<Output = Self> + Sub<Output = Self> {}
+impl<T> Bound for T where T: Ord + Copy + Zero + Add<Output = T> + Sub<Output = T> {}
+
+/// A set of values stored as sorted, disjoint, non-adjacent half open ranges.
+#[derive(Clone, PartialEq, Eq)]
+pub struct IntervalSet<T> {
+    ranges: Vec<Range<T>>,
+}
+
+impl<T> Default for IntervalSet<T> {
+    fn default() -> Self {
+        IntervalSet { ranges: Vec::new() }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for IntervalSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.ranges.iter()).finish()
+    }
+}
+
+impl<T: Bound> From<Range<T>> for IntervalSet<T> {
+    fn from(r: Range<T>) -> Self {
+        let mut s = IntervalSet::new();
+        s.insert(r);
+        s
+    }
+}
+
+impl<T: Bound> std::iter::FromIterator<Range<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = Range<T>>>(iter: I) -> Self {
+        let mut s = IntervalSet::new();
+        for r in iter {
+            s.insert(r);
+        }
+        s
+    }
+}
+
+impl<T: Bound> IntervalSet<T> {
+    pub fn new() -> IntervalSet<T> {
+        IntervalSet::default()
+    }
+
+    pub fn ranges(&self) -> &[Range<T>] {
+        self.ranges.as_slice()
+    }
+    pub fn iter(&self) -> impl Iterator<Item = Range<T>> + '_ {
+        self.ranges.iter().cloned()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+    pub fn min(&self) -> Option<T> {
+        self.ranges.first().map(|r| r.start)
+    }
+
+    pub fn insert(&mut self, r: Range<T>) {
+        if r.start >= r.end {
+            return;
+        }
+        // merge anything overlapping or touching
+        let lo = self.ranges.partition_point(|x| x.end < r.start);
+        let hi = self.ranges.partition_point(|x| x.start <= r.end);
+        let mut merged = r;
+        if lo < hi {
+            merged.start = merged.start.min(self.ranges[lo].start);
+            merged.end = merged.end.max(self.ranges[hi - 1].end);
+        }
+        self.ranges.splice(lo..hi, std::iter::once(merged));
+    }
+
+    pub fn remove(&mut self, r: Range<T>) {
+        if r.start >= r.end {
+            return;
+        }
+        let lo = self.ranges.partition_point(|x| x.end <= r.start);
+        let hi = self.ranges.partition_point(|x| x.start < r.end);
+        if lo >= hi {
+            return;
+        }
+        let mut keep = Vec::with_capacity(2);
+        if self.ranges[lo].start < r.start {
+            keep.push(self.ranges[lo].start..r.start);
+        }
+        if self.ranges[hi - 1].end > r.end {
+            keep.push(r.end..self.ranges[hi - 1].end);
+        }
+        self.ranges.splice(lo..hi, keep);
+    }
+
+    pub fn contains(&self, v: T) -> bool {
+        let idx = self.ranges.partition_point(|x| x.end <= v);
+        self.ranges.get(idx).map(|r| r.start <= v).unwrap_or(false)
+    }
+
+    pub fn union(&self, other: &IntervalSet<T>) -> IntervalSet<T> {
+        let mut s = self.clone();
+        for r in other.iter() {
+            s.insert(r);
+        }
+        s
+    }
+
+    pub fn intersect(&self, other: &IntervalSet<T>) -> IntervalSet<T> {
+        let mut ranges = Vec::new();
+        let (mut a, mut b) = (
+            self.ranges.iter().peekable(),
+            other.ranges.iter().peekable(),
+        );
+        while let (Some(x), Some(y)) = (a.peek(), b.peek()) {
+            let start = x.start.max(y.start);
+            let end = x.end.min(y.end);
+            if start < end {
+                ranges.push(start..end);
+            }
+            if x.end < y.end {
+                a.next();
+            } else {
+                b.next();
+            }
+        }
+        // pieces of disjoint non-adjacent ranges are already normalized
+        IntervalSet { ranges }
+    }
+
+    pub fn difference(&self, other: &IntervalSet<T>) -> IntervalSet<T> {
+        let mut s = self.clone();
+        for r in other.iter() {
+            s.remove(r);
+        }
+        s
+    }
+
+    /// The number of values in the set
+    pub fn total_len(&self) -> T {
+        self.ranges
+            .iter()
+            .fold(T::zero(), |acc, r| acc + (r.end - r.start))
+    }
+
+    /// The parts of `within` that are not in the set
+    pub fn gaps(&self, within: Range<T>) -> IntervalSet<T> {
+        IntervalSet::from(within).difference(self)
+    }
+
+    /// Send every value through a piecewise offset mapping
+    pub fn map_through(&self, map: &OffsetMap<T>) -> IntervalSet<T> {
+        self.iter().flat_map(|r| map.map_range(r)).collect()
+    }
+}
+
+/// Shifts values by a per-range offset, leaving values outside every range unchanged.
+///
+/// This is the shape of the almanac maps: `dest src len` becomes
+/// `insert(src..src + len, dest - src)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OffsetMap<T> {
+    // sorted by start, non-overlapping
+    entries: Vec<(Range<T>, T)>,
+}
+
+impl<T> Default for OffsetMap<T> {
+    fn default() -> Self {
+        OffsetMap {
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<T: Bound> OffsetMap<T> {
+    pub fn new() -> OffsetMap<T> {
+        OffsetMap::default()
+    }
+
+    pub fn insert(&mut self, source: Range<T>, offset: T) -> anyhow::Result<()>
+    where
+        T: fmt::Debug,
+    {
+        if source.start >= source.end {
+            return Ok(());
+        }
+        let idx = self.entries.partition_point(|(r, _)| r.end <= source.start);
+        if let Some((r, _)) = self.entries.get(idx) {
+            if r.start < source.end {
+                anyhow::bail!("source range {:?} overlaps existing range {:?}", source, r);
+            }
+        }
+        self.entries.insert(idx, (source, offset));
+        Ok(())
+    }
+
+    pub fn map_value(&self, v: T) -> T {
+        let idx = self.entries.partition_point(|(r, _)| r.end <= v);
+        match self.entries.get(idx) {
+            Some((r, offset)) if r.start <= v => v + *offset,
+            _ => v,
+        }
+    }
+
+    /// Split `r` at the mapping boundaries and shift each piece.
+    pub fn map_range(&self, r: Range<T>) -> Vec<Range<T>> {
+        let mut out = Vec::new();
+        let mut cursor = r.start;
+        let idx = self.entries.partition_point(|(e, _)| e.end <= r.start);
+        for (e, offset) in &self.entries[idx..] {
+            if cursor >= r.end || e.start >= r.end {
+                break;
+            }
+            if cursor < e.start {
+                out.push(cursor..e.start);
+                cursor = e.start;
+            }
+            let end = e.end.min(r.end);
+            out.push(cursor + *offset..end + *offset);
+            cursor = end;
+        }
+        if cursor < r.end {
+            out.push(cursor..r.end);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(v: &[(i64, i64)]) -> IntervalSet<i64> {
+        v.iter().map(|(a, b)| *a..*b).collect()
+    }
+
+    #[test]
+    fn insert_merges() {
+        let s = set(&[(5, 8), (1, 3), (3, 4), (10, 12), (7, 10)]);
+        assert_eq!(s.ranges(), &[1..4, 5..12]);
+        assert_eq!(s.total_len(), 10);
+        assert!(s.contains(1));
+        assert!(!s.contains(4));
+        assert!(s.contains(11));
+        assert!(!s.contains(12));
+    }
+
+    #[test]
+    fn remove_splits() {
+        let mut s = set(&[(0, 10), (20, 30)]);
+        s.remove(3..5);
+        assert_eq!(s.ranges(), &[0..3, 5..10, 20..30]);
+        s.remove(8..25);
+        assert_eq!(s.ranges(), &[0..3, 5..8, 25..30]);
+        s.remove(-5..100);
+        assert!(s.is_empty());
+    }
+
+    #[test]
+    fn set_algebra() {
+        let a = set(&[(0, 5), (10, 15)]);
+        let b = set(&[(3, 12), (14, 20)]);
+        assert_eq!(a.union(&b), set(&[(0, 20)]));
+        assert_eq!(a.intersect(&b).ranges(), &[3..5, 10..12, 14..15]);
+        assert_eq!(a.difference(&b).ranges(), &[0..3, 12..14]);
+        assert_eq!(a.gaps(-2..13).ranges(), &[-2..0, 5..10]);
+    }
+
+    #[test]
+    fn sensor_row() {
+        // exclusion zones on one row of the beacon puzzle, with the beacon removed
+        let mut row = set(&[(12, 13), (2, 15), (2, 3), (-2, 3), (16, 25), (14, 19)]);
+        row.remove(2..3);
+        assert_eq!(row.total_len(), 26);
+    }
+
+    #[test]
+    fn offset_map_pieces() {
+        let mut m = OffsetMap::new();
+        m.insert(98..100, -48).unwrap();
+        m.insert(50..98, 2).unwrap();
+        assert!(m.insert(60..61, 0).is_err());
+        assert_eq!(m.map_value(79), 81);
+        assert_eq!(m.map_value(99), 51);
+        assert_eq!(m.map_value(10), 10);
+        assert_eq!(m.map_range(40..100), vec![40..50, 52..100, 50..52]);
+        assert_eq!(m.map_range(0..10), vec![0..10]);
+    }
+
+    #[test]
+    fn almanac_lowest_location() {
+        let maps: &[&[(i64, i64, i64)]] = &[
+            &[(50, 98, 2), (52, 50, 48)],
+            &[(0, 15, 37), (37, 52, 2), (39, 0, 15)],
+            &[(49, 53, 8), (0, 11, 42), (42, 0, 7), (57, 7, 4)],
+            &[(88, 18, 7), (18, 25, 70)],
+            &[(45, 77, 23), (81, 45, 19), (68, 64, 13)],
+            &[(0, 69, 1), (1, 0, 69)],
+            &[(60, 56, 37), (56, 93, 4)],
+        ];
+        let maps = maps
+            .iter()
+            .map(|entries| {
+                let mut m = OffsetMap::new();
+                for (dest, src, len) in entries.iter() {
+                    m.insert(*src..*src + *len, *dest - *src).unwrap();
+                }
+                m
+            })
+            .collect::<Vec<_>>();
+        let seeds = set(&[(79, 79 + 14), (55, 55 + 13)]);
+        let locations = maps.iter().fold(seeds, |s, m| s.map_through(m));
+        assert_eq!(locations.min(), Some(46));
+    }
+}
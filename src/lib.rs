@@ -1,21 +1,38 @@
 pub mod grid {
+    pub mod ansi;
     mod bounds;
     pub mod compass;
-    mod coordinates;
+    pub mod cube;
+    pub(crate) mod coordinates;
     pub mod fixed_grid;
     pub mod grid_types;
+    pub mod image;
+    pub mod line;
     pub mod point;
     pub mod pointmap_grid;
+    pub mod recorder;
     pub mod repeat_grid;
+    #[allow(dead_code)]
     mod transform;
+    pub mod walker;
 
     type DefaultCd = i64;
 }
 pub mod ac3;
 pub mod bitset;
+pub mod geometry {
+    pub mod cuboid;
+    pub mod point3;
+    pub mod polygon;
+    pub mod rotation;
+}
+pub mod interval;
+pub mod matching;
+pub mod ocr;
 pub use self::error::Error;
 pub use crate::grid::point::Point;
 
+#[allow(dead_code)]
 pub mod permute;
 
 pub mod parse {
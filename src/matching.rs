@@ -0,0 +1,383 @@
+use crate::ac3::Class;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+    hash::Hash,
+};
+
+/// Classes, each with a set of candidate values, to be matched one to one.
+///
+/// This is the "which allergen is in which ingredient" kind of puzzle: every
+/// class needs a value of its own, and a value can go to at most one class.
+pub struct Bipartite<C, V> {
+    classes: Vec<C>,
+    class_ids: HashMap<C, usize>,
+    values: Vec<V>,
+    value_ids: HashMap<V, usize>,
+    adj: Vec<Vec<usize>>,
+}
+
+impl<C, V> Default for Bipartite<C, V> {
+    fn default() -> Self {
+        Bipartite {
+            classes: Vec::new(),
+            class_ids: HashMap::new(),
+            values: Vec::new(),
+            value_ids: HashMap::new(),
+            adj: Vec::new(),
+        }
+    }
+}
+
+impl<C: Class, V: Clone + Hash + Eq, I: IntoIterator<Item = V>> std::iter::FromIterator<(C, I)>
+    for Bipartite<C, V>
+{
+    fn from_iter<T: IntoIterator<Item = (C, I)>>(iter: T) -> Self {
+        let mut b = Bipartite::new();
+        for (class, values) in iter {
+            b.add_candidates(class, values);
+        }
+        b
+    }
+}
+
+impl<C: Class, V: Clone + Hash + Eq> Bipartite<C, V> {
+    pub fn new() -> Bipartite<C, V> {
+        Bipartite::default()
+    }
+
+    /// Allow `class` to take any of `values`, on top of what it already had
+    pub fn add_candidates<I: IntoIterator<Item = V>>(&mut self, class: C, values: I) {
+        let (classes, adj) = (&mut self.classes, &mut self.adj);
+        let x = *self.class_ids.entry(class).or_insert_with(|| {
+            classes.push(class);
+            adj.push(Vec::new());
+            classes.len() - 1
+        });
+        for value in values {
+            let values = &mut self.values;
+            let v = *self.value_ids.entry(value.clone()).or_insert_with(|| {
+                values.push(value);
+                values.len() - 1
+            });
+            if !self.adj[x].contains(&v) {
+                self.adj[x].push(v);
+            }
+        }
+    }
+
+    /// A matching that gives every class a value, if there is one
+    pub fn perfect_matching(&self) -> Option<Matching<C, V>> {
+        let (var_match, val_match) = hopcroft_karp(&self.adj, self.values.len());
+        let var_match = var_match.into_iter().collect::<Option<Vec<_>>>()?;
+
+        let alternates = alternating_edges(&self.adj, self.values.len(), &var_match, &val_match);
+        let mut assignment = HashMap::new();
+        let mut forced = HashSet::new();
+        for (x, v) in var_match.iter().enumerate() {
+            let class = self.classes[x];
+            assignment.insert(class, self.values[*v].clone());
+            let edge = self.adj[x].iter().position(|e| e == v).unwrap();
+            if !alternates[x][edge] {
+                forced.insert(class);
+            }
+        }
+        Some(Matching { assignment, forced })
+    }
+}
+
+/// One value for every class, no two sharing.
+#[derive(Clone)]
+pub struct Matching<C, V> {
+    assignment: HashMap<C, V>,
+    // classes whose value is the same in every perfect matching
+    forced: HashSet<C>,
+}
+
+impl<C: fmt::Debug, V: fmt::Debug> fmt::Debug for Matching<C, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.assignment.iter()).finish()
+    }
+}
+
+impl<C: Class, V> Matching<C, V> {
+    pub fn get(&self, class: C) -> Option<&V> {
+        self.assignment.get(&class)
+    }
+
+    pub fn assignment(&self) -> &HashMap<C, V> {
+        &self.assignment
+    }
+
+    pub fn into_assignment(self) -> HashMap<C, V> {
+        self.assignment
+    }
+
+    /// True if no other perfect matching exists
+    pub fn is_unique(&self) -> bool {
+        self.forced.len() == self.assignment.len()
+    }
+
+    /// True if `class` gets the same value in every perfect matching
+    pub fn is_forced(&self, class: C) -> bool {
+        self.forced.contains(&class)
+    }
+
+    /// The edges that are part of every perfect matching
+    pub fn forced(&self) -> impl Iterator<Item = (C, &V)> + '_ {
+        self.forced.iter().map(move |c| (*c, &self.assignment[c]))
+    }
+}
+
+/// Maximum matching of classes `0..adj.len()` to values `0..m`, as the value
+/// of each class and the class of each value.
+pub(crate) fn hopcroft_karp(
+    adj: &[Vec<usize>],
+    m: usize,
+) -> (Vec<Option<usize>>, Vec<Option<usize>>) {
+    let n = adj.len();
+    let mut var_match = vec![None; n];
+    let mut val_match = vec![None; m];
+    let mut dist = vec![usize::MAX; n];
+
+    loop {
+        // layer the classes by shortest alternating path from a free class
+        let mut queue = VecDeque::new();
+        for x in 0..n {
+            if var_match[x].is_none() {
+                dist[x] = 0;
+                queue.push_back(x);
+            } else {
+                dist[x] = usize::MAX;
+            }
+        }
+        let mut found = false;
+        while let Some(x) = queue.pop_front() {
+            for v in &adj[x] {
+                match val_match[*v] {
+                    None => found = true,
+                    Some(other) if dist[other] == usize::MAX => {
+                        dist[other] = dist[x] + 1;
+                        queue.push_back(other);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if !found {
+            break;
+        }
+        for x in 0..n {
+            if var_match[x].is_none() {
+                augment(x, adj, &mut var_match, &mut val_match, &mut dist);
+            }
+        }
+    }
+    (var_match, val_match)
+}
+
+fn augment(
+    x: usize,
+    adj: &[Vec<usize>],
+    var_match: &mut [Option<usize>],
+    val_match: &mut [Option<usize>],
+    dist: &mut [usize],
+) -> bool {
+    for v in &adj[x] {
+        let free = match val_match[*v] {
+            None => true,
+            Some(other) => {
+                dist[other] == dist[x] + 1 && augment(other, adj, var_match, val_match, dist)
+            }
+        };
+        if free {
+            var_match[x] = Some(*v);
+            val_match[*v] = Some(x);
+            return true;
+        }
+    }
+    dist[x] = usize::MAX;
+    false
+}
+
+/// For every edge in `adj`, whether some other matching that covers every
+/// class disagrees with the given one about it. `var_match` must cover
+/// every class.
+///
+/// Matched edges go class -> value and the rest value -> class. An edge
+/// alternates if it lies on an alternating cycle, with both ends in one
+/// strongly connected component, or on an even alternating path from a free
+/// value.
+pub(crate) fn alternating_edges(
+    adj: &[Vec<usize>],
+    m: usize,
+    var_match: &[usize],
+    val_match: &[Option<usize>],
+) -> Vec<Vec<bool>> {
+    let n = adj.len();
+    // nodes are the classes, followed by the values
+    let mut graph = vec![Vec::new(); n + m];
+    for (x, values) in adj.iter().enumerate() {
+        for v in values {
+            if var_match[x] == *v {
+                graph[x].push(n + v);
+            } else {
+                graph[n + v].push(x);
+            }
+        }
+    }
+
+    let mut reached = vec![false; n + m];
+    let mut stack = (0..m)
+        .filter(|v| val_match[*v].is_none())
+        .map(|v| n + v)
+        .collect::<Vec<_>>();
+    while let Some(node) = stack.pop() {
+        if !reached[node] {
+            reached[node] = true;
+            stack.extend(graph[node].iter().filter(|next| !reached[**next]));
+        }
+    }
+
+    let component = strongly_connected(&graph);
+    adj.iter()
+        .enumerate()
+        .map(|(x, values)| {
+            values
+                .iter()
+                .map(|v| reached[n + v] || component[x] == component[n + v])
+                .collect()
+        })
+        .collect()
+}
+
+/// Tarjan's algorithm, returning a component id for every node
+fn strongly_connected(graph: &[Vec<usize>]) -> Vec<usize> {
+    struct Tarjan<'a> {
+        graph: &'a [Vec<usize>],
+        index: Vec<Option<usize>>,
+        low: Vec<usize>,
+        on_stack: Vec<bool>,
+        stack: Vec<usize>,
+        component: Vec<usize>,
+        next_index: usize,
+        next_component: usize,
+    }
+
+    impl Tarjan<'_> {
+        fn visit(&mut self, node: usize) {
+            self.index[node] = Some(self.next_index);
+            self.low[node] = self.next_index;
+            self.next_index += 1;
+            self.stack.push(node);
+            self.on_stack[node] = true;
+
+            for &next in &self.graph[node] {
+                match self.index[next] {
+                    None => {
+                        self.visit(next);
+                        self.low[node] = self.low[node].min(self.low[next]);
+                    }
+                    Some(idx) if self.on_stack[next] => {
+                        self.low[node] = self.low[node].min(idx);
+                    }
+                    _ => {}
+                }
+            }
+
+            if Some(self.low[node]) == self.index[node] {
+                while let Some(member) = self.stack.pop() {
+                    self.on_stack[member] = false;
+                    self.component[member] = self.next_component;
+                    if member == node {
+                        break;
+                    }
+                }
+                self.next_component += 1;
+            }
+        }
+    }
+
+    let n = graph.len();
+    let mut t = Tarjan {
+        graph,
+        index: vec![None; n],
+        low: vec![0; n],
+        on_stack: vec![false; n],
+        stack: Vec::new(),
+        component: vec![0; n],
+        next_index: 0,
+        next_component: 0,
+    };
+    for node in 0..n {
+        if t.index[node].is_none() {
+            t.visit(node);
+        }
+    }
+    t.component
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allergens() {
+        let b = vec![
+            ("dairy", vec!["mxmxvkd"]),
+            ("fish", vec!["mxmxvkd", "sqjhc"]),
+            ("soy", vec!["sqjhc", "fvjkl"]),
+        ]
+        .into_iter()
+        .collect::<Bipartite<_, _>>();
+        let m = b.perfect_matching().unwrap();
+        assert!(m.is_unique());
+        assert_eq!(m.get("dairy"), Some(&"mxmxvkd"));
+        assert_eq!(m.get("fish"), Some(&"sqjhc"));
+        assert_eq!(m.get("soy"), Some(&"fvjkl"));
+    }
+
+    #[test]
+    fn forced_edges() {
+        // a and b can swap, c has to take 3 either way
+        let mut b = Bipartite::new();
+        b.add_candidates('a', vec![1, 2]);
+        b.add_candidates('b', vec![1, 2]);
+        b.add_candidates('c', vec![1, 2, 3]);
+        let m = b.perfect_matching().unwrap();
+        assert!(!m.is_unique());
+        assert_eq!(m.forced().collect::<Vec<_>>(), vec![('c', &3)]);
+        assert!(!m.is_forced('a'));
+
+        // a free value to fall back on frees up d as well
+        b.add_candidates('d', vec![4, 5]);
+        let m = b.perfect_matching().unwrap();
+        assert!(!m.is_forced('d'));
+        assert!(m.is_forced('c'));
+    }
+
+    #[test]
+    fn no_perfect_matching() {
+        let mut b = Bipartite::new();
+        b.add_candidates(0, vec!['x', 'y']);
+        b.add_candidates(1, vec!['x']);
+        b.add_candidates(2, vec!['y']);
+        assert!(b.perfect_matching().is_none());
+        b.add_candidates(0, vec!['z']);
+        assert_eq!(b.perfect_matching().unwrap().get(0), Some(&'z'));
+    }
+
+    #[test]
+    fn large_chain() {
+        // class i can take i or i + 1, only the identity covers everything
+        let n = 2000;
+        let b = (0..n)
+            .map(|i| (i, vec![i, i + 1]))
+            .chain(std::iter::once((n, vec![0])))
+            .collect::<Bipartite<_, _>>();
+        let m = b.perfect_matching().unwrap();
+        assert_eq!(m.get(n), Some(&0));
+        assert!((0..n).all(|i| m.get(i) == Some(&(i + 1))));
+        assert!(m.is_unique());
+    }
+}
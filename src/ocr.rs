@@ -0,0 +1,292 @@
+//! Reads the block capital letters that some puzzles draw as their answer.
+use crate::grid::{fixed_grid::FixedGrid, pointmap_grid::PointMap};
+use crate::Point;
+use std::collections::{BTreeSet, HashSet};
+
+/// The 4 wide, 6 tall font (some letters are 3 or 5 wide), drawn every 5 columns
+const FONT_6: &[(char, &str)] = &[
+    ('A', ".##.\n#..#\n#..#\n####\n#..#\n#..#"),
+    ('B', "###.\n#..#\n###.\n#..#\n#..#\n###."),
+    ('C', ".##.\n#..#\n#...\n#...\n#..#\n.##."),
+    ('E', "####\n#...\n###.\n#...\n#...\n####"),
+    ('F', "####\n#...\n###.\n#...\n#...\n#..."),
+    ('G', ".##.\n#..#\n#...\n#.##\n#..#\n.###"),
+    ('H', "#..#\n#..#\n####\n#..#\n#..#\n#..#"),
+    ('I', "###\n.#.\n.#.\n.#.\n.#.\n###"),
+    ('J', "..##\n...#\n...#\n...#\n#..#\n.##."),
+    ('K', "#..#\n#.#.\n##..\n#.#.\n#.#.\n#..#"),
+    ('L', "#...\n#...\n#...\n#...\n#...\n####"),
+    ('O', ".##.\n#..#\n#..#\n#..#\n#..#\n.##."),
+    ('P', "###.\n#..#\n#..#\n###.\n#...\n#..."),
+    ('R', "###.\n#..#\n#..#\n###.\n#.#.\n#..#"),
+    ('S', ".###\n#...\n#...\n.##.\n...#\n###."),
+    ('U', "#..#\n#..#\n#..#\n#..#\n#..#\n.##."),
+    ('Y', "#...#\n#...#\n.#.#.\n..#..\n..#..\n..#.."),
+    ('Z', "####\n...#\n..#.\n.#..\n#...\n####"),
+];
+
+/// The 6 wide, 10 tall font, drawn every 8 columns
+const FONT_10: &[(char, &str)] = &[
+    (
+        'A',
+        "..##..\n.#..#.\n#....#\n#....#\n#....#\n######\n#....#\n#....#\n#....#\n#....#",
+    ),
+    (
+        'B',
+        "#####.\n#....#\n#....#\n#....#\n#####.\n#....#\n#....#\n#....#\n#....#\n#####.",
+    ),
+    (
+        'C',
+        ".####.\n#....#\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#....#\n.####.",
+    ),
+    (
+        'E',
+        "######\n#.....\n#.....\n#.....\n#####.\n#.....\n#.....\n#.....\n#.....\n######",
+    ),
+    (
+        'F',
+        "######\n#.....\n#.....\n#.....\n#####.\n#.....\n#.....\n#.....\n#.....\n#.....",
+    ),
+    (
+        'G',
+        ".####.\n#....#\n#.....\n#.....\n#.....\n#..###\n#....#\n#....#\n#...##\n.###.#",
+    ),
+    (
+        'H',
+        "#....#\n#....#\n#....#\n#....#\n######\n#....#\n#....#\n#....#\n#....#\n#....#",
+    ),
+    (
+        'J',
+        "...###\n....#.\n....#.\n....#.\n....#.\n....#.\n....#.\n#...#.\n#...#.\n.###..",
+    ),
+    (
+        'K',
+        "#....#\n#...#.\n#..#..\n#.#...\n##....\n##....\n#.#...\n#..#..\n#...#.\n#....#",
+    ),
+    (
+        'L',
+        "#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n######",
+    ),
+    (
+        'N',
+        "#....#\n##...#\n##...#\n#.#..#\n#.#..#\n#..#.#\n#..#.#\n#...##\n#...##\n#....#",
+    ),
+    (
+        'P',
+        "#####.\n#....#\n#....#\n#....#\n#####.\n#.....\n#.....\n#.....\n#.....\n#.....",
+    ),
+    (
+        'R',
+        "#####.\n#....#\n#....#\n#....#\n#####.\n#..#..\n#...#.\n#...#.\n#....#\n#....#",
+    ),
+    (
+        'X',
+        "#....#\n#....#\n.#..#.\n.#..#.\n..##..\n..##..\n.#..#.\n.#..#.\n#....#\n#....#",
+    ),
+    (
+        'Z',
+        "######\n.....#\n.....#\n....#.\n...#..\n..#...\n.#....\n#.....\n#.....\n######",
+    ),
+];
+
+/// Anything that can report which of its cells are lit.
+///
+/// Rows grow downward, the same orientation used by the `Display` impls.
+pub trait LitPixels {
+    fn lit_pixels(&self) -> Vec<Point<i64>>;
+}
+
+impl LitPixels for FixedGrid<bool> {
+    fn lit_pixels(&self) -> Vec<Point<i64>> {
+        self.points()
+            .zip(self.raw_iter())
+            .filter(|(_, lit)| **lit)
+            .map(|(p, _)| p)
+            .collect()
+    }
+}
+
+/// Every point with an entry is considered lit
+impl<T> LitPixels for PointMap<T> {
+    fn lit_pixels(&self) -> Vec<Point<i64>> {
+        self.iter().map(|(p, _)| *p).collect()
+    }
+}
+
+impl LitPixels for [Point<i64>] {
+    fn lit_pixels(&self) -> Vec<Point<i64>> {
+        self.to_vec()
+    }
+}
+
+impl LitPixels for HashSet<Point<i64>> {
+    fn lit_pixels(&self) -> Vec<Point<i64>> {
+        self.iter().copied().collect()
+    }
+}
+
+/// Decode the letters drawn by the lit pixels.
+///
+/// The font is picked from the height of the drawing. Glyphs are cut at the
+/// font's fixed pitch rather than at blank columns, since a letter may fill
+/// its whole cell; an unknown glyph is reported by the column it starts at
+/// in the input.
+pub fn read_letters<S: LitPixels + ?Sized>(source: &S) -> anyhow::Result<String> {
+    let lit = source.lit_pixels();
+    let (min_x, min_y) = match (lit.iter().map(|p| p.x).min(), lit.iter().map(|p| p.y).min()) {
+        (Some(x), Some(y)) => (x, y),
+        _ => return Ok(String::new()),
+    };
+    let lit = lit
+        .into_iter()
+        .map(|p| Point::new(p.x - min_x, p.y - min_y))
+        .collect::<HashSet<_>>();
+    let height = lit.iter().map(|p| p.y).max().unwrap_or(0) + 1;
+    let (font, pitch) = match height {
+        6 => (FONT_6, 5),
+        10 => (FONT_10, 8),
+        _ => anyhow::bail!(
+            "lit pixels are {} rows tall, known fonts are 6 or 10 rows",
+            height
+        ),
+    };
+
+    let columns = lit.iter().map(|p| p.x).collect::<BTreeSet<_>>();
+    let last = columns.iter().next_back().copied().unwrap_or(0);
+    let mut letters = String::new();
+    for start in (0..=last).step_by(pitch) {
+        // narrow letters leave blank columns at the end of their cell
+        let end = match columns.range(start..start + pitch as i64).next_back() {
+            Some(end) => *end,
+            None => continue,
+        };
+        let glyph = render_glyph(&lit, start, end, height);
+        let letter = font
+            .iter()
+            .find(|(_, g)| *g == glyph)
+            .map(|(c, _)| *c)
+            .ok_or_else(|| {
+                anyhow::anyhow!("unrecognized glyph at column {}:\n{}", start + min_x, glyph)
+            })?;
+        letters.push(letter);
+    }
+    Ok(letters)
+}
+
+fn render_glyph(lit: &HashSet<Point<i64>>, start: i64, end: i64, height: i64) -> String {
+    let mut s = String::new();
+    for y in 0..height {
+        if y != 0 {
+            s.push('\n');
+        }
+        for x in start..=end {
+            s.push(if lit.contains(&Point::new(x, y)) {
+                '#'
+            } else {
+                '.'
+            });
+        }
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> FixedGrid<bool> {
+        FixedGrid::parse_ascii_grid(s, |c| Ok(c == '#')).unwrap()
+    }
+
+    fn draw(word: &str, font: &[(char, &str)], pitch: i64, origin: Point<i64>) -> PointMap<()> {
+        let mut pm = PointMap::default();
+        let mut x0 = origin.x;
+        for letter in word.chars() {
+            let glyph = font.iter().find(|(c, _)| *c == letter).unwrap().1;
+            for (y, line) in glyph.lines().enumerate() {
+                for (x, c) in line.chars().enumerate() {
+                    if c == '#' {
+                        pm.insert(Point::new(x0 + x as i64, origin.y + y as i64), ());
+                    }
+                }
+            }
+            x0 += pitch;
+        }
+        pm
+    }
+
+    #[test]
+    fn read_small_font() {
+        let g = parse(concat!(
+            ".##..###.\n",
+            "#..#.#..#\n",
+            "#..#.###.\n",
+            "####.#..#\n",
+            "#..#.#..#\n",
+            "#..#.###.",
+        ));
+        assert_eq!(read_letters(&g).unwrap(), "AB");
+    }
+
+    #[test]
+    fn full_width_letter() {
+        // Y fills its cell, so nothing but the pitch separates it from the H
+        let g = parse(concat!(
+            "#...##..#\n",
+            "#...##..#\n",
+            ".#.#.####\n",
+            "..#..#..#\n",
+            "..#..#..#\n",
+            "..#..#..#",
+        ));
+        assert_eq!(read_letters(&g).unwrap(), "YH");
+    }
+
+    #[test]
+    fn every_glyph_roundtrips() {
+        let small = FONT_6.iter().map(|(c, _)| *c).collect::<String>();
+        let pm = draw(&small, FONT_6, 5, Point::new(-3, 7));
+        assert_eq!(read_letters(&pm).unwrap(), small);
+
+        let large = FONT_10.iter().map(|(c, _)| *c).collect::<String>();
+        let pm = draw(&large, FONT_10, 8, Point::new(100, -50));
+        assert_eq!(read_letters(&pm).unwrap(), large);
+    }
+
+    #[test]
+    fn unknown_glyph_column() {
+        let g = parse(concat!(
+            ".##..#...\n",
+            "#..#.#...\n",
+            "#..#.#...\n",
+            "####.#...\n",
+            "#..#.#...\n",
+            "#..#.###.",
+        ));
+        let err = read_letters(&g).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("unrecognized glyph at column 5"));
+
+        // columns count from the input, not from the first lit pixel
+        let g = parse(concat!(
+            "...##..#...\n",
+            "..#..#.#...\n",
+            "..#..#.#...\n",
+            "..####.#...\n",
+            "..#..#.#...\n",
+            "..#..#.###.",
+        ));
+        let err = read_letters(&g).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("unrecognized glyph at column 7"));
+    }
+
+    #[test]
+    fn empty_and_wrong_height() {
+        let g = parse("....\n....");
+        assert_eq!(read_letters(&g).unwrap(), "");
+        assert!(read_letters(&parse("#\n#")).is_err());
+    }
+}